@@ -1,4 +1,5 @@
 use crate::{
+    material::{NifFixedFunctionMaterial, NifMaterial},
     nif_animation::SkeletonMap,
     spawner::{NifInstantiated, NifNodeIndex},
 };
@@ -13,7 +14,7 @@ use bevy_ecs::{
 };
 use bevy_log::error;
 use bevy_mesh::{Mesh, Mesh3d, VertexAttributeValues};
-use bevy_pbr::{MeshMaterial3d, StandardMaterial};
+use bevy_pbr::MeshMaterial3d;
 use bevy_render::render_resource::Face;
 use bevy_transform::components::Transform;
 
@@ -54,8 +55,11 @@ pub fn attach_parts(
     skeleton_map: Res<SkeletonMap>,
     mut commands: Commands,
     mut transforms: Query<&mut Transform>,
-    materials_query: Query<(&Mesh3d, &MeshMaterial3d<StandardMaterial>)>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mesh_query: Query<&Mesh3d>,
+    materials_query: Query<&MeshMaterial3d<NifMaterial>>,
+    mut materials: ResMut<Assets<NifMaterial>>,
+    fixed_function_materials_query: Query<&MeshMaterial3d<NifFixedFunctionMaterial>>,
+    mut fixed_function_materials: ResMut<Assets<NifFixedFunctionMaterial>>,
     nif_node_index_q: Query<&NifNodeIndex>,
     mut meshes: ResMut<Assets<Mesh>>,
 ) {
@@ -104,26 +108,36 @@ pub fn attach_parts(
                     }
                     // TODO:: maybe do the normal flipping in a shader so we don't have to clone
                     // the whole mesh
-                    if let Ok((mesh3d, material)) = materials_query.get(*trishape) {
-                        if let Some(mesh) = meshes.get(&mesh3d.0) {
-                            let mut clone_mesh = mesh.clone();
-                            if let Some(VertexAttributeValues::Float32x3(normals)) =
-                                clone_mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL)
-                            {
-                                //Flip normals since we flipped x scale
-                                for normal in normals {
-                                    normal[0] *= -1.0;
-                                    normal[1] *= -1.0;
-                                    normal[2] *= -1.0;
-                                }
+                    if let Ok(mesh3d) = mesh_query.get(*trishape)
+                        && let Some(mesh) = meshes.get(&mesh3d.0)
+                    {
+                        let mut clone_mesh = mesh.clone();
+                        // Meshes that aren't kept on the CPU keep their normals as they are
+                        if let Ok(Some(VertexAttributeValues::Float32x3(normals))) =
+                            clone_mesh.try_attribute_mut_option(Mesh::ATTRIBUTE_NORMAL)
+                        {
+                            //Flip normals since we flipped x scale
+                            for normal in normals {
+                                normal[0] *= -1.0;
+                                normal[1] *= -1.0;
+                                normal[2] *= -1.0;
                             }
                             let mesh_handle = meshes.add(clone_mesh);
                             commands.entity(*trishape).insert(Mesh3d(mesh_handle));
                         }
-                        if let Some(mut standard_material) = materials.get_mut(&material.0) {
-                            standard_material.cull_mode = Some(Face::Front);
-                            standard_material.double_sided = true;
-                        }
+                    }
+                    // Mirroring flips the winding, so the other face has to be culled
+                    if let Ok(material) = materials_query.get(*trishape)
+                        && let Some(mut nif_material) = materials.get_mut(&material.0)
+                    {
+                        nif_material.base.cull_mode = flip_face(nif_material.base.cull_mode);
+                        nif_material.base.double_sided = true;
+                    }
+                    if let Ok(material) = fixed_function_materials_query.get(*trishape)
+                        && let Some(mut nif_material) =
+                            fixed_function_materials.get_mut(&material.0)
+                    {
+                        nif_material.cull_mode = flip_face(nif_material.cull_mode);
                     }
                 }
             }
@@ -155,3 +169,12 @@ pub fn attach_parts(
         }
     }
 }
+
+/// The face to cull once the winding is flipped by a mirroring transform
+fn flip_face(cull_mode: Option<Face>) -> Option<Face> {
+    match cull_mode {
+        Some(Face::Back) => Some(Face::Front),
+        Some(Face::Front) => Some(Face::Back),
+        None => None,
+    }
+}
@@ -0,0 +1,234 @@
+use bevy_camera::Camera;
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    hierarchy::{ChildOf, Children},
+    query::Without,
+    system::Query,
+};
+use bevy_math::{Affine3A, Mat3, Vec3};
+use bevy_transform::components::{GlobalTransform, Transform};
+use nif::BillboardMode;
+
+/// Marks an entity spawned from a `NiBillboardNode`. The entity (and everything under it)
+/// is turned toward the active camera every frame by [`update_nif_billboards`].
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NifBillboard {
+    pub mode: BillboardMode,
+}
+
+/// Rotates billboard entities toward the active camera.
+///
+/// This runs after transform propagation, so it writes the `GlobalTransform` of the billboard
+/// and all of its descendants directly. Every one of them is recomputed from its parent and its
+/// local `Transform`, rather than adjusted from what propagation left, because propagation only
+/// updates the entities that changed. The local `Transform` is left untouched so the authored
+/// node rotation is still used as the starting point the next frame.
+pub fn update_nif_billboards(
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    billboards: Query<(Entity, &NifBillboard)>,
+    mut global_transforms: Query<&mut GlobalTransform, Without<Camera>>,
+    transforms: Query<(&Transform, Option<&NifBillboard>)>,
+    parents: Query<&ChildOf>,
+    children_q: Query<&Children>,
+) {
+    if billboards.is_empty() {
+        return;
+    }
+    // Pick the highest priority active camera, same as what ends up on screen
+    let Some(camera_transform) = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .max_by_key(|(camera, _)| camera.order)
+        .map(|(_, transform)| *transform)
+    else {
+        return;
+    };
+    let (_, camera_rotation, camera_position) = camera_transform.to_scale_rotation_translation();
+    let camera = (Mat3::from_quat(camera_rotation), camera_position);
+
+    for (entity, _) in &billboards {
+        // Billboards under another billboard are handled with the outer subtree
+        if parents
+            .iter_ancestors(entity)
+            .any(|ancestor| billboards.contains(ancestor))
+        {
+            continue;
+        }
+        let parent_affine = parents
+            .get(entity)
+            .ok()
+            .and_then(|child_of| global_transforms.get(child_of.parent()).ok())
+            .map_or(Affine3A::IDENTITY, GlobalTransform::affine);
+        let mut stack = vec![(entity, parent_affine)];
+        while let Some((current, parent_affine)) = stack.pop() {
+            let Ok((transform, billboard)) = transforms.get(current) else {
+                continue;
+            };
+            let mut affine = parent_affine * transform.compute_affine();
+            if let Some(billboard) = billboard {
+                affine = billboard_affine(billboard.mode, affine, camera).unwrap_or(affine);
+            }
+            if let Ok(mut global_transform) = global_transforms.get_mut(current) {
+                *global_transform = GlobalTransform::from(affine);
+            }
+            if let Ok(children) = children_q.get(current) {
+                stack.extend(children.iter().map(|&child| (child, affine)));
+            }
+        }
+    }
+}
+
+/// Turns the world transform of a billboard toward the camera, keeping its position and scale
+fn billboard_affine(
+    mode: BillboardMode,
+    affine: Affine3A,
+    (camera_rotation, camera_position): (Mat3, Vec3),
+) -> Option<Affine3A> {
+    let (scale, rotation, translation) = affine.to_scale_rotation_translation();
+    let rotation = billboard_rotation(
+        mode,
+        Mat3::from_quat(rotation),
+        translation,
+        camera_rotation,
+        camera_position,
+    )?;
+    Some(Affine3A::from_mat3_translation(
+        rotation * Mat3::from_diagonal(scale),
+        translation,
+    ))
+}
+
+/// Computes the world rotation a billboard should have.
+///
+/// Billboard geometry is authored so that its local X/Y axes line up with the screen and its
+/// local +Z points back at the viewer, which matches the Bevy camera convention.
+fn billboard_rotation(
+    mode: BillboardMode,
+    current_rotation: Mat3,
+    position: Vec3,
+    camera_rotation: Mat3,
+    camera_position: Vec3,
+) -> Option<Mat3> {
+    match mode {
+        // Align with the view plane, all billboards on screen share the same orientation
+        BillboardMode::AlwaysFaceCamera => Some(camera_rotation),
+        // Only spin around the node's own up axis, like trees or torches
+        BillboardMode::RotateAboutUp => {
+            let up = current_rotation.y_axis.try_normalize()?;
+            let to_camera = camera_position - position;
+            let z_axis = (to_camera - up * to_camera.dot(up)).try_normalize()?;
+            let x_axis = up.cross(z_axis);
+            Some(Mat3::from_cols(x_axis, up, z_axis))
+        }
+        // Point at the camera position rather than aligning with the view plane, keeping the
+        // camera's up direction
+        BillboardMode::RigidFaceCamera | BillboardMode::AlwaysFaceCenter => {
+            let z_axis = (camera_position - position).try_normalize()?;
+            let x_axis = camera_rotation.y_axis.cross(z_axis).try_normalize()?;
+            let y_axis = z_axis.cross(x_axis);
+            Some(Mat3::from_cols(x_axis, y_axis, z_axis))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_app::{App, PostUpdate};
+    use bevy_ecs::schedule::IntoScheduleConfigs;
+    use bevy_math::Quat;
+    use bevy_transform::{TransformPlugin, TransformSystems};
+
+    fn assert_affine_eq(a: Affine3A, b: Affine3A) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn test_billboard_moving_child() {
+        let mut app = App::new();
+        app.add_plugins(TransformPlugin).add_systems(
+            PostUpdate,
+            update_nif_billboards.after(TransformSystems::Propagate),
+        );
+        app.world_mut()
+            .spawn((Camera::default(), Transform::from_xyz(0.0, 0.0, 10.0)));
+        let billboard = app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(1.0, 0.0, 0.0).with_rotation(Quat::from_rotation_y(1.0)),
+                NifBillboard {
+                    mode: BillboardMode::AlwaysFaceCamera,
+                },
+            ))
+            .id();
+        let child = app
+            .world_mut()
+            .spawn((Transform::from_xyz(0.0, 1.0, 0.0), ChildOf(billboard)))
+            .id();
+
+        let global =
+            |app: &App, entity| app.world().get::<GlobalTransform>(entity).unwrap().affine();
+        for child_transform in [
+            None,
+            // Only the child changes, so propagation recomputes it from the turned billboard
+            Some(Transform::from_xyz(0.0, 2.0, 0.0).with_rotation(Quat::from_rotation_z(0.5))),
+        ] {
+            if let Some(child_transform) = child_transform {
+                *app.world_mut().get_mut::<Transform>(child).unwrap() = child_transform;
+            }
+            app.update();
+            let billboard_global = global(&app, billboard);
+            // The camera looks down -Z without any rotation
+            assert_affine_eq(billboard_global, Affine3A::from_translation(Vec3::X));
+            let child_local = app
+                .world()
+                .get::<Transform>(child)
+                .unwrap()
+                .compute_affine();
+            assert_affine_eq(global(&app, child), billboard_global * child_local);
+        }
+    }
+
+    #[test]
+    fn test_billboard_rotation_about_up() {
+        // Keeps the node's up axis and turns its +Z toward the camera within that plane
+        let rotation = billboard_rotation(
+            BillboardMode::RotateAboutUp,
+            Mat3::IDENTITY,
+            Vec3::ZERO,
+            Mat3::IDENTITY,
+            Vec3::new(10.0, 5.0, 0.0),
+        )
+        .unwrap();
+        assert!(rotation.y_axis.abs_diff_eq(Vec3::Y, 1e-5));
+        assert!(rotation.z_axis.abs_diff_eq(Vec3::X, 1e-5));
+        assert!(rotation.x_axis.abs_diff_eq(Vec3::NEG_Z, 1e-5));
+
+        // Looking straight down the up axis leaves no direction to turn toward
+        assert!(
+            billboard_rotation(
+                BillboardMode::RotateAboutUp,
+                Mat3::IDENTITY,
+                Vec3::ZERO,
+                Mat3::IDENTITY,
+                Vec3::new(0.0, 5.0, 0.0),
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_billboard_rotation_rigid_face_camera() {
+        let rotation = billboard_rotation(
+            BillboardMode::RigidFaceCamera,
+            Mat3::IDENTITY,
+            Vec3::ZERO,
+            Mat3::IDENTITY,
+            Vec3::new(0.0, 0.0, -4.0),
+        )
+        .unwrap();
+        assert!(rotation.z_axis.abs_diff_eq(Vec3::NEG_Z, 1e-5));
+        assert!(rotation.y_axis.abs_diff_eq(Vec3::Y, 1e-5));
+    }
+}
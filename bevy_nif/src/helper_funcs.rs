@@ -1,18 +1,14 @@
 use std::{env, path::PathBuf};
 
-pub fn resolve_nif_path(nif_path: &str) -> Option<String> {
+/// Find the asset path of a texture a NIF names, relative to the `texture_root` folder
+pub fn resolve_nif_path(nif_path: &str, texture_root: &str) -> Option<String> {
     let cleaned = clean_path_common(nif_path);
 
     if cleaned.is_empty() {
         return None;
     }
 
-    // Strip "textures/" if it exists and normalize to "Textures/"
-    let base = if cleaned.len() >= 9 && cleaned[..9].eq_ignore_ascii_case("textures/") {
-        format!("Textures/{}", &cleaned[9..])
-    } else {
-        format!("Textures/{}", cleaned)
-    };
+    let base = texture_asset_path(&cleaned, texture_root);
     if check_exists(&base) {
         return Some(base);
     }
@@ -20,6 +16,18 @@ pub fn resolve_nif_path(nif_path: &str) -> Option<String> {
     // 2. Try lowercase extension
     return check_exists_lowercase_extension(&base);
 }
+/// Strip "textures/" if it exists and put the texture root in its place
+fn texture_asset_path(cleaned: &str, texture_root: &str) -> String {
+    let texture_root = texture_root.trim_end_matches('/');
+    if cleaned
+        .get(..9)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("textures/"))
+    {
+        format!("{}/{}", texture_root, &cleaned[9..])
+    } else {
+        format!("{}/{}", texture_root, cleaned)
+    }
+}
 pub fn prepend_asset_path(str: &str) -> PathBuf {
     let mut path = env::var("CARGO_MANIFEST_DIR")
         .map(PathBuf::from)
@@ -63,3 +71,23 @@ pub const fn hash_str(s: &str) -> u64 {
     }
     hash
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_texture_asset_path() {
+        let path = |nif_path| texture_asset_path(&clean_path_common(nif_path), "Textures");
+        assert_eq!(path("textures\\tx_wood.dds"), "Textures/tx_wood.dds");
+        assert_eq!(path("TEXTURES\\tx_wood.dds"), "Textures/tx_wood.dds");
+        assert_eq!(path(" tx_wood.dds "), "Textures/tx_wood.dds");
+        assert_eq!(path("textures"), "Textures/textures");
+        // Slicing the prefix must not split a multi byte character
+        assert_eq!(path("текстуры.dds"), "Textures/текстуры.dds");
+        assert_eq!(
+            texture_asset_path("textures/tx_wood.dds", "mods/Textures/"),
+            "mods/Textures/tx_wood.dds"
+        );
+    }
+}
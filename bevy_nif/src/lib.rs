@@ -1,21 +1,34 @@
 pub mod attach_parts;
+pub mod billboard;
 pub mod helper_funcs;
+pub mod lights;
 pub mod loader;
+pub mod material;
 pub mod nif_animation;
+pub mod sampler;
 pub mod skeleton;
 pub mod spawner;
 pub mod spawning_ni_helpers;
+pub mod texture_effects;
 use attach_parts::attach_parts;
-use bevy_app::{App, Plugin, PreUpdate, Update};
-use bevy_asset::AssetApp;
+use bevy_app::{App, Plugin, PostUpdate, PreUpdate, Update};
+use bevy_asset::{AssetApp, embedded_asset};
 use bevy_ecs::component::Component;
 use bevy_ecs::entity::Entity;
+use bevy_ecs::schedule::IntoScheduleConfigs;
+use bevy_image::{CompressedImageFormatSupport, CompressedImageFormats};
+use bevy_pbr::MaterialPlugin;
+use bevy_transform::TransformSystems;
+use billboard::update_nif_billboards;
 pub use helper_funcs::*;
+use lights::{animate_nif_lights, attach_nif_lights};
 use loader::{BMPLoader, Nif, NifAssetLoader};
+use material::{NifFixedFunctionMaterial, NifMaterial};
 pub use nif::types::*;
 use nif_animation::SkeletonMap;
 use nif_animation::animation_setup_system::setup_animations;
-use spawner::spawn_nif_scenes;
+use sampler::{NifSampledImages, update_nif_sampled_images};
+use spawner::{NifColorlessMeshes, prune_nif_colorless_meshes, spawn_nif_scenes};
 
 use crate::loader::DDSLoader;
 #[derive(Component)]
@@ -23,13 +36,42 @@ pub struct NeedsNifPhysics(pub Vec<(Entity, NiKey)>);
 pub struct BevyNifPlugin;
 impl Plugin for BevyNifPlugin {
     fn build(&self, app: &mut App) {
-        app.init_asset::<Nif>()
-            .init_asset_loader::<NifAssetLoader>()
-            .init_asset_loader::<BMPLoader>()
-            .init_asset_loader::<DDSLoader>()
-            .insert_resource(SkeletonMap::default())
-            .add_observer(attach_parts)
-            .add_systems(Update, spawn_nif_scenes)
-            .add_systems(PreUpdate, setup_animations);
+        embedded_asset!(app, "shaders/nif_material.wgsl");
+        embedded_asset!(app, "shaders/nif_fixed_function.wgsl");
+        app.add_plugins((
+            MaterialPlugin::<NifMaterial>::default(),
+            MaterialPlugin::<NifFixedFunctionMaterial>::default(),
+        ))
+        .init_asset::<Nif>()
+        .init_asset_loader::<NifAssetLoader>()
+        .init_asset_loader::<BMPLoader>()
+        .insert_resource(SkeletonMap::default())
+        .init_resource::<NifColorlessMeshes>()
+        .init_resource::<NifSampledImages>()
+        .add_observer(attach_parts)
+        .add_observer(attach_nif_lights)
+        .add_systems(
+            Update,
+            (
+                spawn_nif_scenes,
+                update_nif_sampled_images.after(spawn_nif_scenes),
+                animate_nif_lights,
+                prune_nif_colorless_meshes,
+            ),
+        )
+        .add_systems(PreUpdate, setup_animations)
+        .add_systems(
+            PostUpdate,
+            update_nif_billboards.after(TransformSystems::Propagate),
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        // The render plugin only knows which compressed formats the GPU samples once it's built
+        let supported_compressed_formats = app
+            .world()
+            .get_resource::<CompressedImageFormatSupport>()
+            .map_or(CompressedImageFormats::NONE, |support| support.0);
+        app.register_asset_loader(DDSLoader::new(supported_compressed_formats));
     }
 }
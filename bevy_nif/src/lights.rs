@@ -0,0 +1,410 @@
+//! Conversion of NetImmerse dynamic lights into Bevy lights.
+//!
+//! NetImmerse lights use fixed-function attenuation, `1 / (c + l*d + q*d^2)` scaled by the
+//! light's `dimmer`, while Bevy lights are physically based. The mapping used here is:
+//!
+//! - **colour**: the light's diffuse colour.
+//! - **range**: the distance at which `dimmer * max(diffuse) * attenuation(d)` drops below
+//!   [`NIF_LIGHT_CUTOFF`], one 8 bit colour step. Lights with only constant attenuation never
+//!   fade out and get [`NIF_LIGHT_MAX_RANGE`].
+//! - **intensity**: Bevy point and spot lights fall off with `1 / (4π d²)`. The luminous power
+//!   is chosen so the Bevy light matches the NetImmerse illuminance at a quarter of the range,
+//!   where an unattenuated, full-dimmer NetImmerse light is worth [`NIF_LIGHT_FULL_LUX`].
+//!   This keeps the bright core of the light close to the original while the Bevy range
+//!   cutoff takes care of the tail.
+//! - **spot cone**: `outer_spot_angle` is the NetImmerse cutoff half-angle in degrees. The
+//!   `exponent` falloff (`cos^exponent`) is approximated by putting Bevy's `inner_angle` where
+//!   the NetImmerse falloff reaches half strength.
+//! - **directional**: illuminance is `dimmer * NIF_LIGHT_FULL_LUX`.
+//! - **ambient**: Bevy ambient light belongs to cameras, so `NiAmbientLight` only becomes a
+//!   [`NifAmbientLight`] component for the user to apply however they see fit.
+//!
+//! NetImmerse lights shine down their local +X axis, Bevy lights down their local -Z axis.
+//!
+//! A NetImmerse light only affects the subtrees of nodes that list it in `NiNode::effects`.
+//! That scoping isn't reproduced: Bevy only filters lights by the render layers of the camera,
+//! so a spawned light lights everything the camera sees. Lights that no node lists are spawned
+//! hidden, since they light nothing in NetImmerse.
+//!
+//! Lights that come from outside the NIF, like the light record of a Morrowind torch, are
+//! attached with [`NifAttachLight`] and can flicker or pulse through [`NifLightAnimation`].
+
+use crate::hash_str;
+use crate::spawner::{NifInstantiated, NifNodeIndex};
+use bevy_color::Color;
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    hierarchy::ChildOf,
+    observer::On,
+    system::{Commands, Query, Res},
+};
+use bevy_light::{DirectionalLight, PointLight, SpotLight};
+use bevy_math::{Quat, Vec3};
+use bevy_time::Time;
+use bevy_transform::components::Transform;
+use nif::{NiAVObject, NiLight, NiPointLight, NiType};
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// Illuminance, in lux, of an unattenuated NIF light with a dimmer of 1.0.
+pub const NIF_LIGHT_FULL_LUX: f32 = 1000.0;
+/// Attenuated brightness below which a NIF light no longer contributes.
+pub const NIF_LIGHT_CUTOFF: f32 = 1.0 / 256.0;
+/// Range given to lights whose attenuation never reaches [`NIF_LIGHT_CUTOFF`].
+pub const NIF_LIGHT_MAX_RANGE: f32 = 4096.0;
+
+/// The ambient term of a `NiAmbientLight`, left for the user to apply.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct NifAmbientLight {
+    pub color: Color,
+    pub brightness: f32,
+}
+
+/// Returns true if the `NiType` is one of the light types [`spawn_nif_light`] handles.
+pub fn is_nif_light(ni_type: &NiType) -> bool {
+    matches!(
+        ni_type,
+        NiType::NiPointLight(_)
+            | NiType::NiSpotLight(_)
+            | NiType::NiDirectionalLight(_)
+            | NiType::NiAmbientLight(_)
+    )
+}
+
+/// Spawn the Bevy equivalent of a NIF light as a child of `parent_entity`.
+pub fn spawn_nif_light(
+    ni_type: &NiType,
+    parent_entity: Entity,
+    commands: &mut Commands,
+) -> Option<Entity> {
+    let light: &NiLight = ni_type.try_into().ok()?;
+    let av_object: &NiAVObject = light;
+    let transform = Transform {
+        translation: av_object.translation,
+        // Turn Bevy's -Z light direction onto the NetImmerse +X light direction
+        rotation: Quat::from_mat3(&av_object.rotation.transpose())
+            * Quat::from_rotation_y(-FRAC_PI_2),
+        scale: Vec3::splat(av_object.scale),
+    };
+    let mut entity_commands = commands.spawn((transform, ChildOf(parent_entity)));
+    match ni_type {
+        NiType::NiPointLight(point_light) => {
+            let range = nif_light_range(point_light);
+            entity_commands.insert(PointLight {
+                color: nif_light_color(light),
+                intensity: nif_light_intensity(point_light, range),
+                range,
+                ..Default::default()
+            });
+        }
+        NiType::NiSpotLight(spot_light) => {
+            let range = nif_light_range(&spot_light.base);
+            let outer_angle = spot_light
+                .outer_spot_angle
+                .to_radians()
+                .clamp(0.0, FRAC_PI_2);
+            // Angle at which cos^exponent falls to half strength
+            let inner_angle = if spot_light.exponent > 0.0 {
+                0.5f32.powf(spot_light.exponent.recip()).acos()
+            } else {
+                outer_angle
+            };
+            entity_commands.insert(SpotLight {
+                color: nif_light_color(light),
+                intensity: nif_light_intensity(&spot_light.base, range),
+                range,
+                outer_angle,
+                inner_angle: inner_angle.min(outer_angle),
+                ..Default::default()
+            });
+        }
+        NiType::NiDirectionalLight(_) => {
+            entity_commands.insert(DirectionalLight {
+                color: nif_light_color(light),
+                illuminance: light.dimmer * NIF_LIGHT_FULL_LUX,
+                ..Default::default()
+            });
+        }
+        NiType::NiAmbientLight(_) => {
+            entity_commands.insert(NifAmbientLight {
+                color: Color::srgb(
+                    light.ambient_color.x,
+                    light.ambient_color.y,
+                    light.ambient_color.z,
+                ),
+                brightness: light.dimmer,
+            });
+        }
+        _ => {}
+    }
+    Some(entity_commands.id())
+}
+
+fn nif_light_color(light: &NiLight) -> Color {
+    Color::srgb(
+        light.diffuse_color.x,
+        light.diffuse_color.y,
+        light.diffuse_color.z,
+    )
+}
+
+/// NetImmerse attenuation denominator at distance `d`.
+fn nif_attenuation(light: &NiPointLight, d: f32) -> f32 {
+    light.constant_attenuation + light.linear_attenuation * d + light.quadratic_attenuation * d * d
+}
+
+/// Distance at which the light's contribution falls below [`NIF_LIGHT_CUTOFF`].
+pub fn nif_light_range(light: &NiPointLight) -> f32 {
+    let brightness = light.dimmer * light.diffuse_color.max_element();
+    if brightness <= 0.0 {
+        return 0.0;
+    }
+    // Solve q*d^2 + l*d + c = brightness / cutoff
+    let c = light.constant_attenuation - brightness / NIF_LIGHT_CUTOFF;
+    let l = light.linear_attenuation;
+    let q = light.quadratic_attenuation;
+    let range = if q > 0.0 {
+        (-l + (l * l - 4.0 * q * c).max(0.0).sqrt()) / (2.0 * q)
+    } else if l > 0.0 {
+        -c / l
+    } else {
+        NIF_LIGHT_MAX_RANGE
+    };
+    range.clamp(0.0, NIF_LIGHT_MAX_RANGE)
+}
+
+/// Luminous power, in lumens, matching the NetImmerse illuminance at a quarter of `range`.
+pub fn nif_light_intensity(light: &NiPointLight, range: f32) -> f32 {
+    let reference_distance = (range * 0.25).max(1.0);
+    let attenuation = nif_attenuation(light, reference_distance).max(1.0);
+    let illuminance = light.dimmer * NIF_LIGHT_FULL_LUX / attenuation;
+    4.0 * PI * reference_distance * reference_distance * illuminance
+}
+
+/// Cycles per second of [`NifLightAnimation::Flicker`].
+pub const NIF_FLICKER_SPEED: f32 = 10.0;
+/// Cycles per second of [`NifLightAnimation::FlickerSlow`].
+pub const NIF_FLICKER_SLOW_SPEED: f32 = 3.0;
+/// Cycles per second of [`NifLightAnimation::Pulse`].
+pub const NIF_PULSE_SPEED: f32 = 1.0;
+/// Cycles per second of [`NifLightAnimation::PulseSlow`].
+pub const NIF_PULSE_SLOW_SPEED: f32 = 0.25;
+/// Lowest brightness a flickering light dips to.
+const FLICKER_MIN_BRIGHTNESS: f32 = 0.55;
+/// Lowest brightness a pulsing light dips to.
+const PULSE_MIN_BRIGHTNESS: f32 = 0.3;
+
+/// How the intensity of an attached light changes over time, matching the flicker and pulse
+/// flags of Morrowind light records.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum NifLightAnimation {
+    #[default]
+    None,
+    Flicker,
+    FlickerSlow,
+    Pulse,
+    PulseSlow,
+}
+impl NifLightAnimation {
+    /// Brightness multiplier in `0.0..=1.0` at `time` seconds. `phase` offsets the animation so
+    /// lights spawned together don't move in lockstep.
+    pub fn brightness(self, time: f32, phase: f32) -> f32 {
+        match self {
+            NifLightAnimation::None => 1.0,
+            NifLightAnimation::Flicker => flicker(time * NIF_FLICKER_SPEED + phase),
+            NifLightAnimation::FlickerSlow => flicker(time * NIF_FLICKER_SLOW_SPEED + phase),
+            NifLightAnimation::Pulse => pulse(time * NIF_PULSE_SPEED + phase),
+            NifLightAnimation::PulseSlow => pulse(time * NIF_PULSE_SLOW_SPEED + phase),
+        }
+    }
+}
+
+/// Put this next to a `NifScene` to spawn a point light at one of its nodes once it is
+/// instantiated, like the light of a Morrowind torch or lantern.
+///
+/// The node is looked up by name through the scene's `NifNodeIndex`, if the NIF has no node with
+/// that name the light is placed at the scene root instead.
+#[derive(Component, Clone, Debug)]
+pub struct NifAttachLight {
+    /// Node the light is parented to
+    pub node_name: String,
+    pub color: Color,
+    /// Distance in NIF units the light reaches
+    pub radius: f32,
+    /// Luminous power in lumens, at full brightness
+    pub intensity: f32,
+    pub animation: NifLightAnimation,
+    pub shadows_enabled: bool,
+}
+impl NifAttachLight {
+    /// The node Morrowind torches, lanterns and candles mark their flame with.
+    pub const DEFAULT_NODE_NAME: &'static str = "AttachLight";
+
+    /// A light at the default attach node, with the intensity picked from its `radius` the same
+    /// way [`nif_light_intensity`] does for lights stored in the NIF.
+    pub fn new(color: Color, radius: f32, animation: NifLightAnimation) -> Self {
+        let reference_distance = (radius * 0.25).max(1.0);
+        Self {
+            node_name: Self::DEFAULT_NODE_NAME.to_string(),
+            color,
+            radius,
+            intensity: 4.0 * PI * reference_distance * reference_distance * NIF_LIGHT_FULL_LUX,
+            animation,
+            shadows_enabled: false,
+        }
+    }
+}
+
+/// Added to lights spawned from a [`NifAttachLight`], drives their intensity over time.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct NifLightAnimator {
+    pub base_intensity: f32,
+    pub animation: NifLightAnimation,
+    pub phase: f32,
+}
+
+/// Spawns the light of a [`NifAttachLight`] once its NIF scene is instantiated.
+pub fn attach_nif_lights(
+    event: On<NifInstantiated>,
+    attach_query: Query<(&NifAttachLight, &NifNodeIndex)>,
+    mut commands: Commands,
+) {
+    let Ok((attach_light, nif_index)) = attach_query.get(event.entity) else {
+        return;
+    };
+    let parent_entity = nif_index
+        .named_nodes
+        .get(&hash_str(&attach_light.node_name))
+        .copied()
+        .unwrap_or(event.entity);
+    commands.spawn((
+        Transform::default(),
+        PointLight {
+            color: attach_light.color,
+            intensity: attach_light.intensity,
+            range: attach_light.radius,
+            shadow_maps_enabled: attach_light.shadows_enabled,
+            ..Default::default()
+        },
+        NifLightAnimator {
+            base_intensity: attach_light.intensity,
+            animation: attach_light.animation,
+            phase: light_phase(event.entity),
+        },
+        ChildOf(parent_entity),
+    ));
+}
+
+/// Applies flicker and pulse animations to the intensity of attached lights.
+pub fn animate_nif_lights(
+    time: Res<Time>,
+    mut lights: Query<(&NifLightAnimator, &mut PointLight)>,
+) {
+    // Wrapped so the f32 time keeps enough precision for fast flickers in long sessions
+    let elapsed = time.elapsed_secs_wrapped();
+    for (animator, mut light) in &mut lights {
+        if animator.animation == NifLightAnimation::None {
+            continue;
+        }
+        light.intensity =
+            animator.base_intensity * animator.animation.brightness(elapsed, animator.phase);
+    }
+}
+
+/// A stable per scene offset so neighbouring torches don't flicker in sync.
+fn light_phase(entity: Entity) -> f32 {
+    (hash_noise(entity.to_bits() as u32) * 64.0).fract() * 16.0
+}
+
+/// Smoothly interpolated value noise, two octaves so the flame jitters on top of slower swells.
+fn flicker(t: f32) -> f32 {
+    let noise = value_noise(t) * 0.7 + value_noise(t * 2.7 + 19.0) * 0.3;
+    FLICKER_MIN_BRIGHTNESS + (1.0 - FLICKER_MIN_BRIGHTNESS) * noise
+}
+
+fn pulse(t: f32) -> f32 {
+    let wave = 0.5 + 0.5 * (t * 2.0 * PI).sin();
+    PULSE_MIN_BRIGHTNESS + (1.0 - PULSE_MIN_BRIGHTNESS) * wave
+}
+
+fn value_noise(t: f32) -> f32 {
+    let cell = t.floor();
+    let fraction = t - cell;
+    let smooth = fraction * fraction * (3.0 - 2.0 * fraction);
+    let a = hash_noise(cell as i32 as u32);
+    let b = hash_noise((cell as i32).wrapping_add(1) as u32);
+    a + (b - a) * smooth
+}
+
+/// Integer hash mapped to `0.0..=1.0`.
+fn hash_noise(value: u32) -> f32 {
+    let mut x = value.wrapping_mul(0x9E37_79B9);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x85EB_CA6B);
+    x ^= x >> 13;
+    x = x.wrapping_mul(0xC2B2_AE35);
+    x ^= x >> 16;
+    x as f32 / u32::MAX as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_light(constant: f32, linear: f32, quadratic: f32) -> NiPointLight {
+        NiPointLight {
+            base: NiLight {
+                dimmer: 1.0,
+                diffuse_color: Vec3::ONE,
+                ..Default::default()
+            },
+            constant_attenuation: constant,
+            linear_attenuation: linear,
+            quadratic_attenuation: quadratic,
+        }
+    }
+
+    #[test]
+    fn test_light_range() {
+        // 1 + d = 256
+        assert!((nif_light_range(&point_light(1.0, 1.0, 0.0)) - 255.0).abs() < 1e-3);
+        // 1 + d^2 = 256
+        assert!((nif_light_range(&point_light(1.0, 0.0, 1.0)) - 255f32.sqrt()).abs() < 1e-3);
+        assert_eq!(
+            nif_light_range(&point_light(1.0, 0.0, 0.0)),
+            NIF_LIGHT_MAX_RANGE
+        );
+        let mut dark = point_light(1.0, 1.0, 0.0);
+        dark.base.dimmer = 0.0;
+        assert_eq!(nif_light_range(&dark), 0.0);
+    }
+
+    #[test]
+    fn test_light_intensity() {
+        let light = point_light(1.0, 0.0, 0.01);
+        let range = nif_light_range(&light);
+        let intensity = nif_light_intensity(&light, range);
+        // Bevy's illuminance at a quarter of the range matches the NetImmerse one
+        let d = range * 0.25;
+        let bevy_lux = intensity / (4.0 * PI * d * d);
+        let nif_lux = NIF_LIGHT_FULL_LUX / nif_attenuation(&light, d);
+        assert!((bevy_lux - nif_lux).abs() < 1e-2, "{bevy_lux} != {nif_lux}");
+    }
+
+    #[test]
+    fn test_light_animation_brightness() {
+        assert_eq!(NifLightAnimation::None.brightness(12.3, 4.5), 1.0);
+        // A quarter cycle in, the sine is at its peak
+        assert!((NifLightAnimation::Pulse.brightness(0.25, 0.0) - 1.0).abs() < 1e-5);
+        assert!(
+            (NifLightAnimation::PulseSlow.brightness(3.0, 0.0) - PULSE_MIN_BRIGHTNESS).abs() < 1e-5
+        );
+        for i in 0..1000 {
+            let time = i as f32 * 0.013;
+            for animation in [NifLightAnimation::Flicker, NifLightAnimation::FlickerSlow] {
+                let brightness = animation.brightness(time, 0.7);
+                assert!((FLICKER_MIN_BRIGHTNESS..=1.0).contains(&brightness));
+            }
+        }
+    }
+}
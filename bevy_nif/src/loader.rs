@@ -1,12 +1,18 @@
+use crate::sampler::nif_default_sampler;
 use bevy_asset::RenderAssetUsages;
 use bevy_asset::{AssetLoader, LoadContext, io::Reader};
-use bevy_image::{CompressedImageFormats, Image, ImageSampler, ImageType};
-use bevy_log::error;
+use bevy_image::{CompressedImageFormats, Image};
+use bevy_log::{error, warn};
 use bevy_reflect::TypePath;
 use bevy_render::render_resource::{Extent3d, TextureDimension, TextureFormat};
-use image_dds::ddsfile::Dds;
-pub use nif::loader::Nif;
-use nif::loader::load_nif_bytes;
+use image_dds::ddsfile::{D3DFormat, Dds, DxgiFormat};
+pub use nif::NifEncoding;
+use nif::loader::{ConsumedNiType, load_nif_bytes};
+pub use nif::loader::{
+    Nif, NifCoordinateConversion, NifLoaderSettings, NifNormalGeneration, NifShading,
+};
+use nif::pixel_data::decode_pixel_data;
+use nif::{NiKey, NiType, NifError};
 use std::io::{Cursor, ErrorKind};
 
 #[derive(Default, TypePath)]
@@ -14,21 +20,30 @@ pub struct NifAssetLoader;
 
 impl AssetLoader for NifAssetLoader {
     type Asset = Nif;
-    type Settings = ();
-    type Error = std::io::Error;
+    type Settings = NifLoaderSettings;
+    type Error = NifError;
     async fn load(
         &self,
         reader: &mut dyn Reader,
-        _settings: &(),
+        settings: &NifLoaderSettings,
         load_context: &mut LoadContext<'_>,
     ) -> Result<Self::Asset, Self::Error> {
         let mut bytes = Vec::new();
 
         if let Err(e) = reader.read_to_end(&mut bytes).await {
             error!("NifAssetLoader: Failed to read bytes: {:?}", e);
-            return Err(e);
+            return Err(e.into());
         }
-        load_nif_bytes(&bytes, load_context)
+        let mut nif = load_nif_bytes(&bytes, settings, load_context)?;
+        for diagnostic in &nif.diagnostics {
+            warn!(
+                "NifAssetLoader: {}: {}",
+                load_context.path(),
+                diagnostic.message
+            );
+        }
+        load_embedded_textures(&mut nif, load_context);
+        Ok(nif)
     }
 
     fn extensions(&self) -> &[&str] {
@@ -36,6 +51,50 @@ impl AssetLoader for NifAssetLoader {
     }
 }
 
+/// Decode every NiPixelData into a `texture_{block index}` sub-asset. Source textures find the
+/// image through `Nif::block_assets`.
+fn load_embedded_textures(nif: &mut Nif, load_context: &mut LoadContext<'_>) {
+    let pixel_data_blocks: Vec<(usize, NiKey)> = nif
+        .objects
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, ni_type))| matches!(ni_type, NiType::NiPixelData(_)))
+        .map(|(index, (key, _))| (index, key))
+        .collect();
+    for (index, key) in pixel_data_blocks {
+        let Some(NiType::NiPixelData(pixel_data)) = nif.objects.get(key) else {
+            continue;
+        };
+        let palette = match nif.objects.get(pixel_data.palette.key) {
+            Some(NiType::NiPalette(palette)) => Some(palette),
+            _ => None,
+        };
+        let decoded = match decode_pixel_data(pixel_data, palette) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                error!("NifAssetLoader: Failed to decode embedded texture {index}: {e}");
+                continue;
+            }
+        };
+        let mut image = Image::new_uninit(
+            Extent3d {
+                width: decoded.width,
+                height: decoded.height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.mip_level_count = decoded.mip_level_count;
+        image.data = Some(decoded.rgba);
+        image.sampler = nif_default_sampler();
+        let handle = load_context.add_labeled_asset(format!("texture_{index}"), image);
+        nif.block_assets
+            .insert(key, ConsumedNiType::NiPixelData(handle));
+    }
+}
+
 #[derive(Default, TypePath)]
 pub struct BMPLoader;
 
@@ -76,7 +135,7 @@ impl AssetLoader for BMPLoader {
         }
 
         // Create Bevy Image
-        let image = Image::new(
+        let mut image = Image::new(
             Extent3d {
                 width,
                 height,
@@ -88,6 +147,7 @@ impl AssetLoader for BMPLoader {
             TextureFormat::Rgba8UnormSrgb,
             RenderAssetUsages::default(),
         );
+        image.sampler = nif_default_sampler();
 
         Ok(image)
     }
@@ -96,8 +156,20 @@ impl AssetLoader for BMPLoader {
         &["BMP", "bmp"] // Register for uppercase .BMP also
     }
 }
-#[derive(Default, TypePath)]
-pub struct DDSLoader;
+/// Loads DDS textures. Block compressed textures stay compressed on the GPU with their whole
+/// mip chain, anything the GPU can't sample is decoded to RGBA8 on the CPU instead.
+#[derive(TypePath)]
+pub struct DDSLoader {
+    supported_compressed_formats: CompressedImageFormats,
+}
+
+impl DDSLoader {
+    pub fn new(supported_compressed_formats: CompressedImageFormats) -> Self {
+        Self {
+            supported_compressed_formats,
+        }
+    }
+}
 
 impl AssetLoader for DDSLoader {
     type Asset = Image;
@@ -108,7 +180,7 @@ impl AssetLoader for DDSLoader {
         &self,
         reader: &mut dyn Reader,
         _settings: &Self::Settings,
-        _load_context: &mut LoadContext<'_>,
+        load_context: &mut LoadContext<'_>,
     ) -> Result<Self::Asset, Self::Error> {
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes).await?;
@@ -117,47 +189,161 @@ impl AssetLoader for DDSLoader {
         let dds = Dds::read(&mut Cursor::new(&bytes)).map_err(|e| {
             std::io::Error::new(ErrorKind::Other, format!("DDS parse error: {:?}", e))
         })?;
+        if dds.get_depth() > 1 {
+            return Err(std::io::Error::other(
+                "DDS volume textures aren't supported",
+            ));
+        }
+        if dds.get_num_array_layers() > 1 {
+            warn!(
+                "{}: only the first layer of a DDS array or cube map is loaded",
+                load_context.path()
+            );
+        }
 
-        //  Decode mip 0 into an RgbaImage
-        let rgba_image = image_dds::image_from_dds(&dds, 0).map_err(|e| {
-            std::io::Error::new(ErrorKind::Other, format!("DDS decode error: {:?}", e))
-        })?;
+        // Block compressed formats need the top level to be whole blocks
+        match dds_block_compressed_format(&dds) {
+            Some(format)
+                if self.supported_compressed_formats.supports(format)
+                    && dds.get_width() % 4 == 0
+                    && dds.get_height() % 4 == 0 =>
+            {
+                compressed_dds_image(&dds, format)
+            }
+            _ => decoded_dds_image(&dds),
+        }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["DDS", "dds"]
+    }
+}
 
-        let width = rgba_image.width();
-        let height = rgba_image.height();
-        let rgba_data = rgba_image.into_raw();
-
-        // If the header width/height multiplication doesn't match
-        // the actual byte length (due to block padding), we override the dimensions
-        // or let Bevy take the exact buffer length safely.
-        let expected_len = (width * height * 4) as usize;
-        let actual_len = rgba_data.len();
-
-        let (final_width, final_height) = if expected_len != actual_len {
-            // If there's padding mismatch, recalculate height based on actual buffer bytes
-            let corrected_height = (actual_len / 4) / width as usize;
-            (width, corrected_height as u32)
-        } else {
-            (width, height)
+/// The GPU format of a block compressed DDS. Color formats are always sRGB, like every other
+/// NIF texture, the one and two channel formats hold data and stay linear.
+fn dds_block_compressed_format(dds: &Dds) -> Option<TextureFormat> {
+    if let Some(format) = dds.get_dxgi_format() {
+        return match format {
+            DxgiFormat::BC1_Typeless | DxgiFormat::BC1_UNorm | DxgiFormat::BC1_UNorm_sRGB => {
+                Some(TextureFormat::Bc1RgbaUnormSrgb)
+            }
+            DxgiFormat::BC2_Typeless | DxgiFormat::BC2_UNorm | DxgiFormat::BC2_UNorm_sRGB => {
+                Some(TextureFormat::Bc2RgbaUnormSrgb)
+            }
+            DxgiFormat::BC3_Typeless | DxgiFormat::BC3_UNorm | DxgiFormat::BC3_UNorm_sRGB => {
+                Some(TextureFormat::Bc3RgbaUnormSrgb)
+            }
+            DxgiFormat::BC4_Typeless | DxgiFormat::BC4_UNorm => Some(TextureFormat::Bc4RUnorm),
+            DxgiFormat::BC4_SNorm => Some(TextureFormat::Bc4RSnorm),
+            DxgiFormat::BC5_Typeless | DxgiFormat::BC5_UNorm => Some(TextureFormat::Bc5RgUnorm),
+            DxgiFormat::BC5_SNorm => Some(TextureFormat::Bc5RgSnorm),
+            DxgiFormat::BC7_Typeless | DxgiFormat::BC7_UNorm | DxgiFormat::BC7_UNorm_sRGB => {
+                Some(TextureFormat::Bc7RgbaUnormSrgb)
+            }
+            _ => None,
         };
+    }
+    match dds.get_d3d_format()? {
+        D3DFormat::DXT1 => Some(TextureFormat::Bc1RgbaUnormSrgb),
+        D3DFormat::DXT2 | D3DFormat::DXT3 => Some(TextureFormat::Bc2RgbaUnormSrgb),
+        D3DFormat::DXT4 | D3DFormat::DXT5 => Some(TextureFormat::Bc3RgbaUnormSrgb),
+        _ => None,
+    }
+}
 
-        // Create Bevy Image with correct size and data length
-        let image = Image::new(
-            Extent3d {
-                width: final_width,
-                height: final_height,
-                depth_or_array_layers: 1,
-            },
-            TextureDimension::D2,
-            rgba_data,
-            TextureFormat::Rgba8UnormSrgb,
-            RenderAssetUsages::default(),
-        );
+/// Upload the blocks of the first layer as they are, mips included
+fn compressed_dds_image(dds: &Dds, format: TextureFormat) -> std::io::Result<Image> {
+    let width = dds.get_width();
+    let height = dds.get_height();
+    let mip_level_count = dds.get_num_mipmap_levels().max(1);
+    let block_size = format.block_copy_size(None).unwrap_or(16);
+    let layer_len = mip_chain_len(width, height, mip_level_count, 4, block_size);
+    let Some(data) = dds.data.get(..layer_len) else {
+        return Err(std::io::Error::other("DDS mip chain is truncated"));
+    };
+    let mut image = Image::new_uninit(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        format,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.mip_level_count = mip_level_count;
+    image.sampler = nif_default_sampler();
+    image.data = Some(data.to_vec());
+    Ok(image)
+}
 
-        Ok(image)
+/// Decode every mip of the first layer to RGBA8 on the CPU
+fn decoded_dds_image(dds: &Dds) -> std::io::Result<Image> {
+    let surface = image_dds::Surface::from_dds(dds)
+        .map_err(|e| std::io::Error::other(format!("DDS decode error: {:?}", e)))?;
+    let rgba = surface
+        .decode_rgba8()
+        .map_err(|e| std::io::Error::other(format!("DDS decode error: {:?}", e)))?;
+    let layer_len = mip_chain_len(rgba.width, rgba.height, rgba.mipmaps, 1, 4);
+    let Some(data) = rgba.data.get(..layer_len) else {
+        return Err(std::io::Error::other("DDS mip chain is truncated"));
+    };
+    let mut image = Image::new_uninit(
+        Extent3d {
+            width: rgba.width,
+            height: rgba.height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.mip_level_count = rgba.mipmaps.max(1);
+    image.sampler = nif_default_sampler();
+    image.data = Some(data.to_vec());
+    Ok(image)
+}
+
+/// Bytes taken by a whole mip chain, each level rounded up to whole blocks of
+/// `block_dim` texels square
+fn mip_chain_len(
+    width: u32,
+    height: u32,
+    mip_level_count: u32,
+    block_dim: u32,
+    block_size: u32,
+) -> usize {
+    (0..mip_level_count.max(1))
+        .map(|level| {
+            let blocks_wide = (width >> level).max(1).div_ceil(block_dim);
+            let blocks_high = (height >> level).max(1).div_ceil(block_dim);
+            (blocks_wide * blocks_high * block_size) as usize
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dds_block_size() {
+        let block_size = |format: TextureFormat| format.block_copy_size(None);
+        assert_eq!(block_size(TextureFormat::Bc1RgbaUnormSrgb), Some(8));
+        assert_eq!(block_size(TextureFormat::Bc2RgbaUnormSrgb), Some(16));
+        assert_eq!(block_size(TextureFormat::Bc3RgbaUnormSrgb), Some(16));
+        assert_eq!(block_size(TextureFormat::Bc4RUnorm), Some(8));
+        assert_eq!(block_size(TextureFormat::Bc5RgUnorm), Some(16));
+        assert_eq!(block_size(TextureFormat::Bc7RgbaUnormSrgb), Some(16));
     }
 
-    fn extensions(&self) -> &[&str] {
-        &["DDS", "dds"]
+    #[test]
+    fn test_mip_chain_len() {
+        // 8x8 BC1 down to 1x1: 4 + 1 + 1 + 1 blocks of 8 bytes, the small mips still take a whole block
+        assert_eq!(mip_chain_len(8, 8, 4, 4, 8), 56);
+        // Non square RGBA8 chain: 4x2, 2x1, 1x1
+        assert_eq!(mip_chain_len(4, 2, 3, 1, 4), (8 + 2 + 1) * 4);
+        // A missing mip count still means the top level
+        assert_eq!(mip_chain_len(16, 16, 0, 4, 16), 256);
     }
 }
@@ -0,0 +1,325 @@
+use bevy_asset::{Asset, AssetPath, Handle, embedded_path};
+use bevy_image::Image;
+use bevy_material::AlphaMode;
+use bevy_math::Vec4;
+use bevy_mesh::MeshVertexBufferLayoutRef;
+use bevy_pbr::{
+    ExtendedMaterial, Material, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline,
+    MaterialPipeline, MaterialPipelineKey, StandardMaterial,
+};
+use bevy_reflect::Reflect;
+use bevy_render::render_resource::{
+    AsBindGroup, CompareFunction, Face, RenderPipelineDescriptor, ShaderType,
+    SpecializedMeshPipelineError,
+};
+use bevy_shader::ShaderRef;
+
+/// The material every NiTriShape is spawned with. The base holds everything StandardMaterial
+/// can express, the extension carries the NIF render state it can't.
+pub type NifMaterial = ExtendedMaterial<StandardMaterial, NifMaterialExtension>;
+
+/// Render state and texture slots from NIF properties that StandardMaterial has no fields for.
+/// The base texture goes in `StandardMaterial::base_color_texture`, every other
+/// NiTexturingProperty slot is bound here and combined in `nif_material.wgsl`.
+#[derive(Asset, AsBindGroup, Reflect, Clone, Debug)]
+#[bind_group_data(NifMaterialKey)]
+pub struct NifMaterialExtension {
+    /// `NiZBufferProperty::z_buffer_test`, when false the shape draws over everything
+    pub depth_test: bool,
+    /// `NiZBufferProperty::function` turned around for Bevy's reverse-Z depth buffer
+    #[reflect(ignore, clone)]
+    pub depth_compare: CompareFunction,
+    /// `NiZBufferProperty::z_buffer_write`, when false the shape doesn't occlude anything
+    pub depth_write: bool,
+    #[uniform(100)]
+    pub texture_settings: NifTextureSettings,
+    /// Multiplies the base color, usually baked lighting
+    #[texture(101)]
+    #[sampler(102)]
+    pub dark_texture: Option<Handle<Image>>,
+    /// Multiplies the base color by twice its value, so mid grey leaves it unchanged
+    #[texture(103)]
+    #[sampler(104)]
+    pub detail_texture: Option<Handle<Image>>,
+    /// Scales the specular reflectance
+    #[texture(105)]
+    #[sampler(106)]
+    pub gloss_texture: Option<Handle<Image>>,
+    /// Added on top of the lit color
+    #[texture(107)]
+    #[sampler(108)]
+    pub glow_texture: Option<Handle<Image>>,
+    /// Environment bump map, perturbs environment map lookups
+    #[texture(109)]
+    #[sampler(110)]
+    pub bump_texture: Option<Handle<Image>>,
+    /// Blended over the base color by its own alpha
+    #[texture(111)]
+    #[sampler(112)]
+    pub decal_texture: Option<Handle<Image>>,
+    /// Sphere map of an environment map NiTextureEffect, added on top of the lit color
+    #[texture(113)]
+    #[sampler(114)]
+    pub env_texture: Option<Handle<Image>>,
+}
+impl Default for NifMaterialExtension {
+    fn default() -> Self {
+        Self {
+            depth_test: true,
+            depth_compare: CompareFunction::GreaterEqual,
+            depth_write: true,
+            texture_settings: NifTextureSettings::default(),
+            dark_texture: None,
+            detail_texture: None,
+            gloss_texture: None,
+            glow_texture: None,
+            bump_texture: None,
+            decal_texture: None,
+            env_texture: None,
+        }
+    }
+}
+
+/// Which texture slots are filled and the UV set each one reads, mirrored in
+/// `nif_material.wgsl`.
+#[derive(ShaderType, Reflect, Clone, Copy, Debug, Default)]
+pub struct NifTextureSettings {
+    /// `NIF_TEXTURE_*` bits of the slots that have a texture
+    pub flags: u32,
+    pub dark_uv_set: u32,
+    pub detail_uv_set: u32,
+    pub gloss_uv_set: u32,
+    pub glow_uv_set: u32,
+    pub bump_uv_set: u32,
+    pub decal_uv_set: u32,
+    pub bump_luma_scale: f32,
+    pub bump_luma_offset: f32,
+    /// The bump map's 2x2 displacement matrix, column major
+    pub bump_matrix: Vec4,
+}
+pub const NIF_TEXTURE_DARK: u32 = 1 << 0;
+pub const NIF_TEXTURE_DETAIL: u32 = 1 << 1;
+pub const NIF_TEXTURE_GLOSS: u32 = 1 << 2;
+pub const NIF_TEXTURE_GLOW: u32 = 1 << 3;
+pub const NIF_TEXTURE_BUMP: u32 = 1 << 4;
+pub const NIF_TEXTURE_DECAL: u32 = 1 << 5;
+pub const NIF_TEXTURE_ENV: u32 = 1 << 6;
+
+/// The part of [`NifMaterialExtension`] that changes the render pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NifMaterialKey {
+    depth_test: bool,
+    depth_compare: CompareFunction,
+    depth_write: bool,
+}
+impl From<&NifMaterialExtension> for NifMaterialKey {
+    fn from(extension: &NifMaterialExtension) -> Self {
+        Self {
+            depth_test: extension.depth_test,
+            depth_compare: extension.depth_compare,
+            depth_write: extension.depth_write,
+        }
+    }
+}
+
+impl MaterialExtension for NifMaterialExtension {
+    fn fragment_shader() -> ShaderRef {
+        ShaderRef::Path(
+            AssetPath::from_path_buf(embedded_path!("shaders/nif_material.wgsl"))
+                .with_source("embedded"),
+        )
+    }
+
+    fn specialize(
+        _pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        apply_depth_state(
+            descriptor,
+            key.bind_group_data.depth_test,
+            key.bind_group_data.depth_compare,
+            key.bind_group_data.depth_write,
+        );
+        Ok(())
+    }
+}
+
+/// Set the depth test and depth writes of a pipeline as an NiZBufferProperty asks.
+fn apply_depth_state(
+    descriptor: &mut RenderPipelineDescriptor,
+    depth_test: bool,
+    depth_compare: CompareFunction,
+    depth_write: bool,
+) {
+    let Some(depth_stencil) = descriptor.depth_stencil.as_mut() else {
+        return;
+    };
+    depth_stencil.depth_compare = Some(if depth_test {
+        depth_compare
+    } else {
+        CompareFunction::Always
+    });
+    if !depth_write {
+        depth_stencil.depth_write_enabled = Some(false);
+    }
+}
+
+pub const NIF_FIXED_FUNCTION_SPECULAR: u32 = 1 << 0;
+pub const NIF_FIXED_FUNCTION_SMOOTH: u32 = 1 << 1;
+pub const NIF_FIXED_FUNCTION_VERTEX_EMISSIVE: u32 = 1 << 2;
+pub const NIF_FIXED_FUNCTION_VERTEX_AMBIENT_DIFFUSE: u32 = 1 << 3;
+pub const NIF_FIXED_FUNCTION_EMISSIVE_ONLY: u32 = 1 << 4;
+pub const NIF_FIXED_FUNCTION_ALPHA_MASK: u32 = 1 << 5;
+pub const NIF_FIXED_FUNCTION_BASE_TEXTURE: u32 = 1 << 6;
+pub const NIF_FIXED_FUNCTION_BASE_UV_1: u32 = 1 << 7;
+
+/// The NiMaterialProperty colors and lighting switches of a [`NifFixedFunctionMaterial`],
+/// mirrored in `nif_fixed_function.wgsl`.
+#[derive(ShaderType, Reflect, Clone, Copy, Debug, Default)]
+pub struct NifFixedFunctionUniform {
+    /// Linear colors, the diffuse alpha is the material alpha
+    pub ambient: Vec4,
+    pub diffuse: Vec4,
+    pub specular: Vec4,
+    pub emissive: Vec4,
+    /// Specular exponent
+    pub shine: f32,
+    /// Used when `NIF_FIXED_FUNCTION_ALPHA_MASK` is set
+    pub alpha_cutoff: f32,
+    /// `ApplyMode` of the NiTexturingProperty as its integer value
+    pub apply_mode: u32,
+    /// `NIF_FIXED_FUNCTION_*` bits
+    pub flags: u32,
+}
+
+/// A Morrowind faithful alternative to [`NifMaterial`], picked with `NifShading::FixedFunction`.
+///
+/// Lighting is per pixel, but otherwise follows the fixed function pipeline: ambient, diffuse
+/// and emissive terms from the NiMaterialProperty, optional Blinn-Phong specular, vertex colors
+/// standing in for material colors as NiVertexColorProperty asks and the base texture combined
+/// with the lit color by the NiTexturingProperty apply mode.
+#[derive(Asset, AsBindGroup, Reflect, Clone, Debug)]
+#[bind_group_data(NifFixedFunctionKey)]
+pub struct NifFixedFunctionMaterial {
+    #[uniform(0)]
+    pub uniform: NifFixedFunctionUniform,
+    #[texture(1)]
+    #[sampler(2)]
+    pub base_texture: Option<Handle<Image>>,
+    #[uniform(3)]
+    pub texture_settings: NifTextureSettings,
+    #[texture(4)]
+    #[sampler(5)]
+    pub dark_texture: Option<Handle<Image>>,
+    #[texture(6)]
+    #[sampler(7)]
+    pub detail_texture: Option<Handle<Image>>,
+    #[texture(8)]
+    #[sampler(9)]
+    pub gloss_texture: Option<Handle<Image>>,
+    #[texture(10)]
+    #[sampler(11)]
+    pub glow_texture: Option<Handle<Image>>,
+    #[texture(12)]
+    #[sampler(13)]
+    pub bump_texture: Option<Handle<Image>>,
+    #[texture(14)]
+    #[sampler(15)]
+    pub decal_texture: Option<Handle<Image>>,
+    #[texture(16)]
+    #[sampler(17)]
+    pub env_texture: Option<Handle<Image>>,
+    pub alpha_mode: AlphaMode,
+    #[reflect(ignore, clone)]
+    pub cull_mode: Option<Face>,
+    pub depth_test: bool,
+    #[reflect(ignore, clone)]
+    pub depth_compare: CompareFunction,
+    pub depth_write: bool,
+}
+impl Default for NifFixedFunctionMaterial {
+    fn default() -> Self {
+        Self::from_extension(NifMaterialExtension::default())
+    }
+}
+impl NifFixedFunctionMaterial {
+    /// Take over the texture slots and depth state already gathered for a [`NifMaterial`]
+    pub fn from_extension(extension: NifMaterialExtension) -> Self {
+        Self {
+            uniform: NifFixedFunctionUniform {
+                diffuse: Vec4::ONE,
+                ..Default::default()
+            },
+            base_texture: None,
+            texture_settings: extension.texture_settings,
+            dark_texture: extension.dark_texture,
+            detail_texture: extension.detail_texture,
+            gloss_texture: extension.gloss_texture,
+            glow_texture: extension.glow_texture,
+            bump_texture: extension.bump_texture,
+            decal_texture: extension.decal_texture,
+            env_texture: extension.env_texture,
+            alpha_mode: AlphaMode::Opaque,
+            cull_mode: Some(Face::Back),
+            depth_test: extension.depth_test,
+            depth_compare: extension.depth_compare,
+            depth_write: extension.depth_write,
+        }
+    }
+}
+
+/// The part of [`NifFixedFunctionMaterial`] that changes the render pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NifFixedFunctionKey {
+    cull_mode: Option<Face>,
+    depth_test: bool,
+    depth_compare: CompareFunction,
+    depth_write: bool,
+}
+impl From<&NifFixedFunctionMaterial> for NifFixedFunctionKey {
+    fn from(material: &NifFixedFunctionMaterial) -> Self {
+        Self {
+            cull_mode: material.cull_mode,
+            depth_test: material.depth_test,
+            depth_compare: material.depth_compare,
+            depth_write: material.depth_write,
+        }
+    }
+}
+
+impl Material for NifFixedFunctionMaterial {
+    fn fragment_shader() -> ShaderRef {
+        ShaderRef::Path(
+            AssetPath::from_path_buf(embedded_path!("shaders/nif_fixed_function.wgsl"))
+                .with_source("embedded"),
+        )
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    // The default prepass shader knows nothing about the base texture alpha, so alpha tested
+    // shapes would be written to the depth prepass whole
+    fn enable_prepass() -> bool {
+        false
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        descriptor.primitive.cull_mode = key.bind_group_data.cull_mode;
+        apply_depth_state(
+            descriptor,
+            key.bind_group_data.depth_test,
+            key.bind_group_data.depth_compare,
+            key.bind_group_data.depth_write,
+        );
+        Ok(())
+    }
+}
@@ -26,7 +26,6 @@ use bevy_log::{info, warn};
 use bevy_math::{Quat, Vec3};
 use bevy_transform::components::Transform;
 use nif::{NiKey, NiKeyframeController, NiTextKey};
-use slotmap::Key;
 use std::collections::{HashMap, HashSet};
 
 use super::SkeletonMap;
@@ -106,9 +105,9 @@ pub fn setup_animations(
 
         // --- STEP 1: Extract Bone Controllers ---
         let mut all_bone_controllers: HashMap<NiKey, Vec<NiKeyframeController>> = HashMap::new();
-        for (target_key, kfc) in &nif_asset.all_controller_links {
+        for kfc in nif_asset.objects_of_type::<NiKeyframeController>() {
             all_bone_controllers
-                .entry(*target_key)
+                .entry(kfc.target.key)
                 .or_default()
                 .push(kfc.clone());
         }
@@ -150,12 +149,7 @@ pub fn setup_animations(
             };
 
             for controller in controllers {
-                let keyframe_data_key = controller.data.key;
-                if keyframe_data_key.is_null() {
-                    continue;
-                }
-                let Some(keyframe_data) = nif_asset.all_keyframe_data.get(&keyframe_data_key)
-                else {
+                let Some(keyframe_data) = nif_asset.get(controller.data) else {
                     continue;
                 };
 
@@ -258,14 +252,9 @@ pub fn setup_animations(
 
             let mut keys = Vec::new();
             for controller in bip01_controllers {
-                let keyframe_data_key = controller.data.key;
-                //dbg!(keyframe_data_key);
+                //dbg!(controller.data);
                 //dbg!("no kfd");
-                if keyframe_data_key.is_null() {
-                    continue;
-                }
-
-                if let Some(kfd) = nif_asset.all_keyframe_data.get(&keyframe_data_key) {
+                if let Some(kfd) = nif_asset.get(controller.data) {
                     // Apply NiPosKey pattern matching for translation data
                     let pos_keys_enum = &kfd.translations.keys;
 
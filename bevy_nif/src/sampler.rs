@@ -0,0 +1,196 @@
+//! Texture maps and texture effects pick their own addressing and filtering through `ClampMode`
+//! and `FilterMode`. A Bevy image carries its sampler, and the asset server hands out one image
+//! per path no matter the loader settings, so every combination a NIF asks for gets its own copy
+//! of the image. The NIF image loaders already set the default `WrapSWrapT` and `Trilerp`
+//! sampler, which needs no copy.
+use std::collections::{HashMap, HashSet};
+
+use bevy_asset::{AssetEvent, AssetId, AssetServer, Assets, Handle};
+use bevy_ecs::{
+    message::MessageReader,
+    resource::Resource,
+    system::{Res, ResMut},
+};
+use bevy_image::{Image, ImageAddressMode, ImageFilterMode, ImageSampler, ImageSamplerDescriptor};
+use bevy_log::error;
+use nif::{ClampMode, FilterMode};
+
+/// Copies of loaded images with the sampler of a texture map, shared between every NIF that
+/// samples an image the same way.
+#[derive(Resource, Default)]
+pub struct NifSampledImages {
+    /// The materials own the copies, an entry is dropped once its copy goes unused
+    images: HashMap<(AssetId<Image>, ClampMode, FilterMode), AssetId<Image>>,
+    /// Copies whose source image hasn't finished loading
+    pending: Vec<PendingSampledImage>,
+}
+struct PendingSampledImage {
+    source: Handle<Image>,
+    target: Handle<Image>,
+    sampler: ImageSamplerDescriptor,
+}
+
+impl NifSampledImages {
+    /// The copy of `source` sampled with `clamp_mode` and `filter_mode`. It's filled in by
+    /// [`update_nif_sampled_images`] once the source image is loaded. The
+    /// [`nif_default_sampler`] the NIF image loaders already set gets `source` back.
+    pub fn sampled(
+        &mut self,
+        images: &mut Assets<Image>,
+        source: Handle<Image>,
+        clamp_mode: ClampMode,
+        filter_mode: FilterMode,
+    ) -> Handle<Image> {
+        if (clamp_mode, filter_mode) == (ClampMode::WrapSWrapT, FilterMode::Trilerp) {
+            return source;
+        }
+        let key = (source.id(), clamp_mode, filter_mode);
+        if let Some(&copy_id) = self.images.get(&key) {
+            let pending = self
+                .pending
+                .iter()
+                .find(|pending| pending.target.id() == copy_id);
+            if let Some(pending) = pending {
+                return pending.target.clone();
+            }
+            if let Some(copy) = images.get_strong_handle(copy_id) {
+                return copy;
+            }
+        }
+        let target = images.reserve_handle();
+        self.images.insert(key, target.id());
+        self.pending.push(PendingSampledImage {
+            source,
+            target: target.clone(),
+            sampler: nif_sampler_descriptor(clamp_mode, filter_mode),
+        });
+        target
+    }
+}
+
+/// The sampler texture maps default to, wrapping in both directions with trilinear filtering.
+pub fn nif_default_sampler() -> ImageSampler {
+    ImageSampler::Descriptor(nif_sampler_descriptor(
+        ClampMode::WrapSWrapT,
+        FilterMode::Trilerp,
+    ))
+}
+
+/// Translate a texture map's clamp and filter modes into a sampler
+pub fn nif_sampler_descriptor(
+    clamp_mode: ClampMode,
+    filter_mode: FilterMode,
+) -> ImageSamplerDescriptor {
+    let (address_mode_u, address_mode_v) = match clamp_mode {
+        ClampMode::ClampSClampT => (ImageAddressMode::ClampToEdge, ImageAddressMode::ClampToEdge),
+        ClampMode::ClampSWrapT => (ImageAddressMode::ClampToEdge, ImageAddressMode::Repeat),
+        ClampMode::WrapSClampT => (ImageAddressMode::Repeat, ImageAddressMode::ClampToEdge),
+        ClampMode::WrapSWrapT => (ImageAddressMode::Repeat, ImageAddressMode::Repeat),
+    };
+    // (filter within a mip level, filter between mip levels, uses mips)
+    let (filter, mipmap_filter, mipmapped) = match filter_mode {
+        FilterMode::Nearest => (ImageFilterMode::Nearest, ImageFilterMode::Nearest, false),
+        FilterMode::Bilerp => (ImageFilterMode::Linear, ImageFilterMode::Nearest, false),
+        FilterMode::Trilerp => (ImageFilterMode::Linear, ImageFilterMode::Linear, true),
+        FilterMode::NearestMipNearest => (ImageFilterMode::Nearest, ImageFilterMode::Nearest, true),
+        FilterMode::NearestMipLerp => (ImageFilterMode::Nearest, ImageFilterMode::Linear, true),
+        FilterMode::BilerpMipNearest => (ImageFilterMode::Linear, ImageFilterMode::Nearest, true),
+    };
+    ImageSamplerDescriptor {
+        label: Some("nif_texture_map".to_string()),
+        address_mode_u,
+        address_mode_v,
+        address_mode_w: ImageAddressMode::Repeat,
+        mag_filter: filter,
+        min_filter: filter,
+        mipmap_filter,
+        // Without mipmapping only the top level is ever sampled
+        lod_max_clamp: if mipmapped { 32.0 } else { 0.0 },
+        ..Default::default()
+    }
+}
+
+/// Fill in the sampled copies whose source image has loaded and forget copies no material uses
+/// anymore.
+pub fn update_nif_sampled_images(
+    mut sampled_images: ResMut<NifSampledImages>,
+    mut images: ResMut<Assets<Image>>,
+    mut image_events: MessageReader<AssetEvent<Image>>,
+    asset_server: Res<AssetServer>,
+) {
+    let sampled_images = &mut *sampled_images;
+    sampled_images.pending.retain(|pending| {
+        if let Some(source) = images.get(&pending.source) {
+            let mut image = source.clone();
+            image.sampler = ImageSampler::Descriptor(pending.sampler.clone());
+            if let Err(err) = images.insert(&pending.target, image) {
+                error!("Couldn't insert sampled NIF texture: {err}");
+            }
+            return false;
+        }
+        // A source that failed to load would otherwise stay pending forever
+        !asset_server.load_state(&pending.source).is_failed()
+    });
+    let unused: HashSet<_> = image_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Unused { id } | AssetEvent::Removed { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    if !unused.is_empty() {
+        sampled_images
+            .images
+            .retain(|_, copy_id| !unused.contains(copy_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampler_descriptor() {
+        let descriptor = nif_sampler_descriptor(ClampMode::ClampSWrapT, FilterMode::Nearest);
+        assert_eq!(descriptor.address_mode_u, ImageAddressMode::ClampToEdge);
+        assert_eq!(descriptor.address_mode_v, ImageAddressMode::Repeat);
+        assert_eq!(descriptor.mag_filter, ImageFilterMode::Nearest);
+        assert_eq!(descriptor.lod_max_clamp, 0.0);
+
+        let descriptor =
+            nif_sampler_descriptor(ClampMode::WrapSClampT, FilterMode::BilerpMipNearest);
+        assert_eq!(descriptor.address_mode_u, ImageAddressMode::Repeat);
+        assert_eq!(descriptor.address_mode_v, ImageAddressMode::ClampToEdge);
+        assert_eq!(descriptor.min_filter, ImageFilterMode::Linear);
+        assert_eq!(descriptor.mipmap_filter, ImageFilterMode::Nearest);
+        assert!(descriptor.lod_max_clamp > 0.0);
+    }
+
+    #[test]
+    fn test_sampled_images_are_shared() {
+        let mut images = Assets::<Image>::default();
+        let mut sampled_images = NifSampledImages::default();
+        let source = images.add(Image::default());
+        // The loaders already give images the default sampler
+        let sampled = sampled_images.sampled(
+            &mut images,
+            source.clone(),
+            ClampMode::WrapSWrapT,
+            FilterMode::Trilerp,
+        );
+        assert_eq!(sampled.id(), source.id());
+
+        let mut sample = || {
+            sampled_images.sampled(
+                &mut images,
+                source.clone(),
+                ClampMode::ClampSClampT,
+                FilterMode::Trilerp,
+            )
+        };
+        let first = sample();
+        let second = sample();
+        assert_ne!(first.id(), source.id());
+        assert_eq!(first.id(), second.id());
+    }
+}
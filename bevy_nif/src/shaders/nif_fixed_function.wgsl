@@ -0,0 +1,299 @@
+// Fragment shader of NifFixedFunctionMaterial, a per pixel take on the NetImmerse fixed
+// function pipeline: emissive + ambient + diffuse lighting, optional specular, then the base
+// texture combined with the lit color by the NiTexturingProperty apply mode.
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings as view_bindings,
+    mesh_view_types::POINT_LIGHT_FLAGS_SPOT_LIGHT_Y_NEGATIVE,
+    clustered_forward as clustering,
+    lighting::getDistanceAttenuation,
+    pbr_types,
+    pbr_functions::main_pass_post_lighting_processing,
+    view_transformations::direction_world_to_view,
+}
+
+// Mirrors NifFixedFunctionUniform
+struct NifFixedFunctionUniform {
+    ambient: vec4<f32>,
+    diffuse: vec4<f32>,
+    specular: vec4<f32>,
+    emissive: vec4<f32>,
+    shine: f32,
+    alpha_cutoff: f32,
+    apply_mode: u32,
+    flags: u32,
+}
+
+// Mirrors NifTextureSettings
+struct NifTextureSettings {
+    flags: u32,
+    dark_uv_set: u32,
+    detail_uv_set: u32,
+    gloss_uv_set: u32,
+    glow_uv_set: u32,
+    bump_uv_set: u32,
+    decal_uv_set: u32,
+    bump_luma_scale: f32,
+    bump_luma_offset: f32,
+    bump_matrix: vec4<f32>,
+}
+
+const PI: f32 = 3.141592653589793;
+
+const NIF_FIXED_FUNCTION_SPECULAR: u32 = 1u;
+const NIF_FIXED_FUNCTION_SMOOTH: u32 = 2u;
+const NIF_FIXED_FUNCTION_VERTEX_EMISSIVE: u32 = 4u;
+const NIF_FIXED_FUNCTION_VERTEX_AMBIENT_DIFFUSE: u32 = 8u;
+const NIF_FIXED_FUNCTION_EMISSIVE_ONLY: u32 = 16u;
+const NIF_FIXED_FUNCTION_ALPHA_MASK: u32 = 32u;
+const NIF_FIXED_FUNCTION_BASE_TEXTURE: u32 = 64u;
+const NIF_FIXED_FUNCTION_BASE_UV_1: u32 = 128u;
+
+const NIF_TEXTURE_DARK: u32 = 1u;
+const NIF_TEXTURE_DETAIL: u32 = 2u;
+const NIF_TEXTURE_GLOSS: u32 = 4u;
+const NIF_TEXTURE_GLOW: u32 = 8u;
+const NIF_TEXTURE_BUMP: u32 = 16u;
+const NIF_TEXTURE_DECAL: u32 = 32u;
+const NIF_TEXTURE_ENV: u32 = 64u;
+
+// ApplyMode
+const APPLY_REPLACE: u32 = 0u;
+const APPLY_DECAL: u32 = 1u;
+const APPLY_MODULATE: u32 = 2u;
+const APPLY_HILIGHT: u32 = 3u;
+const APPLY_HILIGHT2: u32 = 4u;
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<uniform> material: NifFixedFunctionUniform;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1) var base_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(2) var base_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(3) var<uniform> nif_textures: NifTextureSettings;
+@group(#{MATERIAL_BIND_GROUP}) @binding(4) var dark_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(5) var dark_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(6) var detail_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(7) var detail_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(8) var gloss_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(9) var gloss_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(10) var glow_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(11) var glow_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(12) var bump_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(13) var bump_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(14) var decal_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(15) var decal_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(16) var env_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(17) var env_sampler: sampler;
+
+fn has_flag(flag: u32) -> bool {
+    return (material.flags & flag) != 0u;
+}
+
+fn has_texture(flag: u32) -> bool {
+    return (nif_textures.flags & flag) != 0u;
+}
+
+fn nif_uv(in: VertexOutput, uv_set: u32) -> vec2<f32> {
+#ifdef VERTEX_UVS_B
+    if uv_set == 1u {
+        return in.uv_b;
+    }
+#endif
+#ifdef VERTEX_UVS_A
+    return in.uv;
+#else
+    return vec2<f32>(0.0);
+#endif
+}
+
+// Sphere map lookup of the reflected view direction, offset by the environment bump map
+fn nif_environment(in: VertexOutput, N: vec3<f32>, V: vec3<f32>) -> vec3<f32> {
+    let r = direction_world_to_view(reflect(-V, N));
+    let m = 2.0 * sqrt(r.x * r.x + r.y * r.y + (r.z + 1.0) * (r.z + 1.0));
+    var uv = vec2<f32>(r.x / m + 0.5, 0.5 - r.y / m);
+    var luma = 1.0;
+    if has_texture(NIF_TEXTURE_BUMP) {
+        let bump = textureSample(bump_texture, bump_sampler, nif_uv(in, nif_textures.bump_uv_set));
+        let displacement = nif_textures.bump_matrix;
+        uv += mat2x2<f32>(displacement.xy, displacement.zw) * bump.rg;
+        luma = saturate(bump.b * nif_textures.bump_luma_scale + nif_textures.bump_luma_offset);
+    }
+    return textureSample(env_texture, env_sampler, uv).rgb * luma;
+}
+
+// Diffuse and specular light reaching the fragment, before the material colors
+struct LightSum {
+    diffuse: vec3<f32>,
+    specular: vec3<f32>,
+}
+
+fn add_light(
+    sum: ptr<function, LightSum>,
+    light_color: vec3<f32>,
+    L: vec3<f32>,
+    N: vec3<f32>,
+    V: vec3<f32>,
+) {
+    let NdotL = max(dot(N, L), 0.0);
+    (*sum).diffuse += light_color * NdotL / PI;
+    if has_flag(NIF_FIXED_FUNCTION_SPECULAR) && NdotL > 0.0 {
+        let H = normalize(L + V);
+        (*sum).specular += light_color * pow(max(dot(N, H), 0.0), max(material.shine, 1.0)) / PI;
+    }
+}
+
+fn gather_lights(in: VertexOutput, N: vec3<f32>, V: vec3<f32>) -> LightSum {
+    var sum: LightSum;
+    sum.diffuse = vec3<f32>(0.0);
+    sum.specular = vec3<f32>(0.0);
+    let P = in.world_position.xyz;
+
+    let view_z = dot(vec4<f32>(
+        view_bindings::view.view_from_world[0].z,
+        view_bindings::view.view_from_world[1].z,
+        view_bindings::view.view_from_world[2].z,
+        view_bindings::view.view_from_world[3].z
+    ), in.world_position);
+    let is_orthographic = view_bindings::view.clip_from_view[3].w == 1.0;
+    let cluster_index = clustering::view_fragment_cluster_index(in.position.xy, view_z, is_orthographic);
+    let ranges = clustering::unpack_clusterable_object_index_ranges(cluster_index);
+
+    // Point and spot lights
+    for (var i: u32 = ranges.first_point_light_index_offset;
+            i < ranges.first_reflection_probe_index_offset;
+            i = i + 1u) {
+        let light_id = clustering::get_clusterable_object_id(i);
+        let light = &view_bindings::clustered_lights.data[light_id];
+        let light_to_frag = (*light).position_radius.xyz - P;
+        let distance_square = dot(light_to_frag, light_to_frag);
+        var attenuation = getDistanceAttenuation(
+            distance_square,
+            (*light).color_inverse_square_range.w,
+        );
+        let L = normalize(light_to_frag);
+        if i >= ranges.first_spot_light_index_offset {
+            var spot_dir = vec3<f32>((*light).light_custom_data.x, 0.0, (*light).light_custom_data.y);
+            spot_dir.y = sqrt(max(0.0, 1.0 - spot_dir.x * spot_dir.x - spot_dir.z * spot_dir.z));
+            if ((*light).flags & POINT_LIGHT_FLAGS_SPOT_LIGHT_Y_NEGATIVE) != 0u {
+                spot_dir.y = -spot_dir.y;
+            }
+            let cone = saturate(dot(-spot_dir, L) * (*light).light_custom_data.z + (*light).light_custom_data.w);
+            attenuation *= cone * cone;
+        }
+        add_light(&sum, (*light).color_inverse_square_range.rgb * attenuation, L, N, V);
+    }
+
+    // Directional lights
+    for (var i: u32 = 0u; i < view_bindings::lights.n_directional_lights; i = i + 1u) {
+        let light = &view_bindings::lights.directional_lights[i];
+        add_light(&sum, (*light).color.rgb, (*light).direction_to_light, N, V);
+    }
+    return sum;
+}
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> @location(0) vec4<f32> {
+    let exposure = view_bindings::view.exposure;
+    let V = normalize(view_bindings::view.world_position.xyz - in.world_position.xyz);
+    var N: vec3<f32>;
+    if has_flag(NIF_FIXED_FUNCTION_SMOOTH) {
+        N = normalize(in.world_normal);
+        if !is_front {
+            N = -N;
+        }
+    } else {
+        // Flat shading, one normal per triangle from the screen space derivatives
+        N = normalize(cross(dpdx(in.world_position.xyz), dpdy(in.world_position.xyz)));
+        if dot(N, V) < 0.0 {
+            N = -N;
+        }
+    }
+
+    var ambient = material.ambient.rgb;
+    var diffuse = material.diffuse.rgb;
+    var emissive = material.emissive.rgb;
+    var alpha = material.diffuse.a;
+#ifdef VERTEX_COLORS
+    if has_flag(NIF_FIXED_FUNCTION_VERTEX_EMISSIVE) {
+        emissive = in.color.rgb;
+        alpha *= in.color.a;
+    } else if has_flag(NIF_FIXED_FUNCTION_VERTEX_AMBIENT_DIFFUSE) {
+        ambient = in.color.rgb;
+        diffuse = in.color.rgb;
+        alpha *= in.color.a;
+    }
+#endif
+
+    // Material colors are display referred like the rest of the fixed function pipeline, only
+    // the light reaching the surface goes through the camera exposure
+    var lit = emissive;
+    var specular = vec3<f32>(0.0);
+    if !has_flag(NIF_FIXED_FUNCTION_EMISSIVE_ONLY) {
+        let lights = gather_lights(in, N, V);
+        lit += ambient * view_bindings::lights.ambient_color.rgb * exposure
+            + diffuse * lights.diffuse * exposure;
+        specular = material.specular.rgb * lights.specular * exposure;
+    }
+
+    var color = vec4<f32>(lit, alpha);
+    if has_flag(NIF_FIXED_FUNCTION_BASE_TEXTURE) {
+        var base_uv_set = 0u;
+        if has_flag(NIF_FIXED_FUNCTION_BASE_UV_1) {
+            base_uv_set = 1u;
+        }
+        var texel = textureSample(base_texture, base_sampler, nif_uv(in, base_uv_set));
+        if has_texture(NIF_TEXTURE_DARK) {
+            let dark = textureSample(dark_texture, dark_sampler, nif_uv(in, nif_textures.dark_uv_set));
+            texel = vec4<f32>(texel.rgb * dark.rgb, texel.a);
+        }
+        if has_texture(NIF_TEXTURE_DETAIL) {
+            let detail = textureSample(detail_texture, detail_sampler, nif_uv(in, nif_textures.detail_uv_set));
+            texel = vec4<f32>(texel.rgb * detail.rgb * 2.0, texel.a);
+        }
+        if has_texture(NIF_TEXTURE_DECAL) {
+            let decal = textureSample(decal_texture, decal_sampler, nif_uv(in, nif_textures.decal_uv_set));
+            texel = vec4<f32>(mix(texel.rgb, decal.rgb, decal.a), texel.a);
+        }
+        switch material.apply_mode {
+            case APPLY_REPLACE: {
+                color = texel;
+            }
+            case APPLY_DECAL: {
+                color = vec4<f32>(mix(color.rgb, texel.rgb, texel.a), color.a);
+            }
+            case APPLY_HILIGHT: {
+                color = vec4<f32>(color.rgb + texel.rgb, color.a * texel.a);
+            }
+            case APPLY_HILIGHT2: {
+                color = vec4<f32>(color.rgb * texel.rgb * 2.0, color.a * texel.a);
+            }
+            default: {
+                color = color * texel;
+            }
+        }
+    }
+    if has_texture(NIF_TEXTURE_GLOSS) {
+        let gloss = textureSample(gloss_texture, gloss_sampler, nif_uv(in, nif_textures.gloss_uv_set));
+        specular *= gloss.r;
+    }
+    color = vec4<f32>(color.rgb + specular, color.a);
+    if has_texture(NIF_TEXTURE_GLOW) {
+        let glow = textureSample(glow_texture, glow_sampler, nif_uv(in, nif_textures.glow_uv_set));
+        color = vec4<f32>(color.rgb + glow.rgb, color.a);
+    }
+    if has_texture(NIF_TEXTURE_ENV) {
+        color = vec4<f32>(color.rgb + nif_environment(in, N, V), color.a);
+    }
+
+    if has_flag(NIF_FIXED_FUNCTION_ALPHA_MASK) && color.a < material.alpha_cutoff {
+        discard;
+    }
+
+    // Fog, tonemapping and alpha premultiplication like every other Bevy material
+    var pbr_input = pbr_types::pbr_input_new();
+    pbr_input.frag_coord = in.position;
+    pbr_input.world_position = in.world_position;
+    pbr_input.material.flags = pbr_types::STANDARD_MATERIAL_FLAGS_FOG_ENABLED_BIT;
+    return main_pass_post_lighting_processing(pbr_input, color);
+}
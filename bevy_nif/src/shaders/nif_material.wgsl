@@ -0,0 +1,144 @@
+// Fragment shader of NifMaterial, layers the extra NiTexturingProperty slots and the
+// environment map on top of StandardMaterial the way the NetImmerse fixed function pipeline
+// combines them.
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::alpha_discard,
+    view_transformations::direction_world_to_view,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+// Mirrors NifTextureSettings
+struct NifTextureSettings {
+    flags: u32,
+    dark_uv_set: u32,
+    detail_uv_set: u32,
+    gloss_uv_set: u32,
+    glow_uv_set: u32,
+    bump_uv_set: u32,
+    decal_uv_set: u32,
+    bump_luma_scale: f32,
+    bump_luma_offset: f32,
+    bump_matrix: vec4<f32>,
+}
+
+const NIF_TEXTURE_DARK: u32 = 1u;
+const NIF_TEXTURE_DETAIL: u32 = 2u;
+const NIF_TEXTURE_GLOSS: u32 = 4u;
+const NIF_TEXTURE_GLOW: u32 = 8u;
+const NIF_TEXTURE_BUMP: u32 = 16u;
+const NIF_TEXTURE_DECAL: u32 = 32u;
+const NIF_TEXTURE_ENV: u32 = 64u;
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(100) var<uniform> nif_textures: NifTextureSettings;
+@group(#{MATERIAL_BIND_GROUP}) @binding(101) var dark_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(102) var dark_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(103) var detail_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(104) var detail_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(105) var gloss_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(106) var gloss_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(107) var glow_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(108) var glow_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(109) var bump_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(110) var bump_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(111) var decal_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(112) var decal_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(113) var env_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(114) var env_sampler: sampler;
+
+fn has_texture(flag: u32) -> bool {
+    return (nif_textures.flags & flag) != 0u;
+}
+
+// The mesh only carries the first two UV sets, anything else was mapped to 0 on the CPU
+fn nif_uv(in: VertexOutput, uv_set: u32) -> vec2<f32> {
+#ifdef VERTEX_UVS_B
+    if uv_set == 1u {
+        return in.uv_b;
+    }
+#endif
+#ifdef VERTEX_UVS_A
+    return in.uv;
+#else
+    return vec2<f32>(0.0);
+#endif
+}
+
+// Sphere map lookup of the reflected view direction, offset by the environment bump map
+fn nif_environment(in: VertexOutput, N: vec3<f32>, V: vec3<f32>) -> vec3<f32> {
+    let r = direction_world_to_view(reflect(-V, N));
+    let m = 2.0 * sqrt(r.x * r.x + r.y * r.y + (r.z + 1.0) * (r.z + 1.0));
+    var uv = vec2<f32>(r.x / m + 0.5, 0.5 - r.y / m);
+    var luma = 1.0;
+    if has_texture(NIF_TEXTURE_BUMP) {
+        let bump = textureSample(bump_texture, bump_sampler, nif_uv(in, nif_textures.bump_uv_set));
+        let displacement = nif_textures.bump_matrix;
+        uv += mat2x2<f32>(displacement.xy, displacement.zw) * bump.rg;
+        luma = saturate(bump.b * nif_textures.bump_luma_scale + nif_textures.bump_luma_offset);
+    }
+    return textureSample(env_texture, env_sampler, uv).rgb * luma;
+}
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    var base_color = pbr_input.material.base_color;
+
+    if has_texture(NIF_TEXTURE_DARK) {
+        let dark = textureSample(dark_texture, dark_sampler, nif_uv(in, nif_textures.dark_uv_set));
+        base_color = vec4<f32>(base_color.rgb * dark.rgb, base_color.a);
+    }
+    if has_texture(NIF_TEXTURE_DETAIL) {
+        let detail = textureSample(detail_texture, detail_sampler, nif_uv(in, nif_textures.detail_uv_set));
+        base_color = vec4<f32>(base_color.rgb * detail.rgb * 2.0, base_color.a);
+    }
+    if has_texture(NIF_TEXTURE_DECAL) {
+        let decal = textureSample(decal_texture, decal_sampler, nif_uv(in, nif_textures.decal_uv_set));
+        base_color = vec4<f32>(mix(base_color.rgb, decal.rgb, decal.a), base_color.a);
+    }
+    pbr_input.material.base_color = base_color;
+
+    if has_texture(NIF_TEXTURE_GLOSS) {
+        let gloss = textureSample(gloss_texture, gloss_sampler, nif_uv(in, nif_textures.gloss_uv_set));
+        pbr_input.material.reflectance = pbr_input.material.reflectance * gloss.r;
+    }
+    if has_texture(NIF_TEXTURE_GLOW) {
+        let glow = textureSample(glow_texture, glow_sampler, nif_uv(in, nif_textures.glow_uv_set));
+        pbr_input.material.emissive = vec4<f32>(
+            pbr_input.material.emissive.rgb + glow.rgb,
+            pbr_input.material.emissive.a,
+        );
+    }
+    if has_texture(NIF_TEXTURE_ENV) {
+        let env = nif_environment(in, pbr_input.N, pbr_input.V);
+        pbr_input.material.emissive = vec4<f32>(
+            pbr_input.material.emissive.rgb + env,
+            pbr_input.material.emissive.a,
+        );
+    }
+
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+    return out;
+}
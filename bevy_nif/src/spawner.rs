@@ -1,34 +1,51 @@
 use crate::attach_parts::AttachmentType;
+use crate::billboard::NifBillboard;
+use crate::lights::{is_nif_light, spawn_nif_light};
+use crate::material::{
+    NIF_FIXED_FUNCTION_ALPHA_MASK, NIF_FIXED_FUNCTION_BASE_TEXTURE, NIF_FIXED_FUNCTION_BASE_UV_1,
+    NIF_FIXED_FUNCTION_SMOOTH, NIF_FIXED_FUNCTION_SPECULAR, NifFixedFunctionMaterial, NifMaterial,
+    NifMaterialExtension,
+};
 use crate::nif_animation::SkeletonMap;
-use crate::spawning_ni_helpers::{process_nimaterialproperty, process_nitexturingproperty};
+use crate::sampler::NifSampledImages;
+use crate::spawning_ni_helpers::{
+    process_fixed_function_material, process_fixed_function_vertex_colors, process_nialphaproperty,
+    process_nimaterialproperty, process_nistencilproperty, process_nitextureeffect,
+    process_nitexturingproperty, process_nivertexcolorproperty, process_nizbufferproperty,
+};
+use crate::texture_effects::{is_nif_projected_texture, spawn_nif_projected_texture};
 use crate::{NeedsNifPhysics, hash_str, skeleton::*};
-use bevy_asset::{AssetServer, Assets, Handle};
+use bevy_asset::{AssetEvent, AssetId, AssetServer, Assets, Handle};
 use bevy_camera::visibility::Visibility;
+use bevy_color::Alpha;
 use bevy_ecs::{
     component::Component,
     entity::Entity,
     event::EntityEvent,
     hierarchy::ChildOf,
+    message::MessageReader,
     name::Name,
     query::Without,
     resource::Resource,
     system::{Commands, Query, Res, ResMut},
 };
+use bevy_image::Image;
 use bevy_log::{error, warn};
 use bevy_material::AlphaMode;
 use bevy_math::{Mat4, Quat, Vec3};
 use bevy_mesh::Mesh3d;
 use bevy_mesh::{
-    Mesh, VertexAttributeValues,
+    Mesh, UvChannel, VertexAttributeValues,
     skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
 };
-use bevy_pbr::{MeshMaterial3d, StandardMaterial};
+use bevy_pbr::{MeshMaterial3d, wireframe::Wireframe};
+use bevy_render::render_resource::Face;
 use bevy_transform::components::Transform;
 use nif::{
-    NiKey, NiSkinInstance, NiType,
-    loader::{ConsumedNiType, Nif},
+    ApplyMode, NiKey, NiNode, NiSkinInstance, NiType,
+    loader::{ConsumedNiType, Nif, NifCoordinateConversion, NifShading},
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::f32::consts::FRAC_PI_2;
 use std::f32::consts::PI;
 #[derive(Component, Default)]
@@ -60,16 +77,20 @@ pub struct NifScene(pub Handle<Nif>);
 
 pub fn spawn_nif_scenes(
     mut commands: Commands,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut materials: ResMut<Assets<NifMaterial>>,
+    mut fixed_function_materials: ResMut<Assets<NifFixedFunctionMaterial>>,
     mut meshes: ResMut<Assets<Mesh>>,
     nif_assets: Res<Assets<Nif>>,
     asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut sampled_images: ResMut<NifSampledImages>,
     mut new_scenes: Query<
         (Entity, &NifScene, Option<&mut AttachmentType>),
         Without<LoadedNifScene>,
     >,
     mut inverse_bindposes: ResMut<Assets<SkinnedMeshInverseBindposes>>,
     mut skeleton_map_res: ResMut<SkeletonMap>,
+    mut colorless_meshes: ResMut<NifColorlessMeshes>,
 ) {
     if new_scenes.is_empty() {
         return;
@@ -77,22 +98,17 @@ pub fn spawn_nif_scenes(
     // is_main_skeleton is just based on if the asset_path contains base_anim.nif
     // default to false
     let mut is_main_skeleton = false;
-    let Some((
-        original_entity,
-        work_root,
-        asset_handle,
-        nif_scene_component,
-        target_skeleton_id_opt,
-    )) = new_scenes
-        .iter_mut()
-        .find_map(|(entity, nif_scene_component, attachment_type_opt)| {
-            let asset_handle = &nif_scene_component.0;
-            let asset_path = asset_handle.path()?.to_string();
+    let Some((original_entity, asset_handle, nif_scene_component, target_skeleton_id_opt)) =
+        new_scenes
+            .iter_mut()
+            .find_map(|(entity, nif_scene_component, attachment_type_opt)| {
+                let asset_handle = &nif_scene_component.0;
+                let asset_path = asset_handle.path()?.to_string();
 
-            is_main_skeleton = asset_path.contains("base_anim.nif");
-            let target_skeleton_id_opt = attachment_type_opt.map(|a| a.get_target_skeleton_id());
-            if !is_main_skeleton {
-                if let Some(id) = target_skeleton_id_opt {
+                is_main_skeleton = asset_path.contains("base_anim.nif");
+                let target_skeleton_id_opt =
+                    attachment_type_opt.map(|a| a.get_target_skeleton_id());
+                if !is_main_skeleton && let Some(id) = target_skeleton_id_opt {
                     let exists = skeleton_map_res.root_skeleton_entity_map.contains_key(&id);
                     if !exists {
                         // If it's not a skeleton asset, and this asset relies on a skeleton
@@ -100,40 +116,41 @@ pub fn spawn_nif_scenes(
                         return None;
                     }
                 }
-            }
-            let mut work_root = entity;
-            if is_main_skeleton {
-                // TODO:: maybe this should happen in the loader, modifying the root node?
-                // We only really want to rotate the main skeleton though, since that will rotate
-                // its children too...
-                let root_rotator_entity = commands
-                    .spawn((
-                        Name::new("rotator entity"),
-                        Transform::from_rotation(
-                            Quat::from_rotation_x(-FRAC_PI_2) * Quat::from_rotation_z(PI),
-                        ),
-                        Visibility::Inherited,
-                        ChildOf(entity),
-                    ))
-                    .id();
-                // add the rotator entity as a child and then set it as the "root"
-                work_root = root_rotator_entity;
-            }
-
-            Some((
-                entity,
-                work_root,
-                asset_handle,
-                nif_scene_component,
-                target_skeleton_id_opt,
-            ))
-        })
+                Some((
+                    entity,
+                    asset_handle,
+                    nif_scene_component,
+                    target_skeleton_id_opt,
+                ))
+            })
     else {
         return;
     };
     let Some(nif) = nif_assets.get(&nif_scene_component.0) else {
         return;
     };
+    let converts_coordinates = match nif.settings.coordinate_conversion {
+        NifCoordinateConversion::MainSkeleton => is_main_skeleton,
+        NifCoordinateConversion::All => true,
+        NifCoordinateConversion::None => false,
+    };
+    let mut work_root = original_entity;
+    if converts_coordinates {
+        // TODO:: maybe this should happen in the loader, modifying the root node?
+        // By default we only rotate the main skeleton, since that will rotate its children too...
+        let root_rotator_entity = commands
+            .spawn((
+                Name::new("rotator entity"),
+                Transform::from_rotation(
+                    Quat::from_rotation_x(-FRAC_PI_2) * Quat::from_rotation_z(PI),
+                ),
+                Visibility::Inherited,
+                ChildOf(original_entity),
+            ))
+            .id();
+        // add the rotator entity as a child and then set it as the "root"
+        work_root = root_rotator_entity;
+    }
 
     let mut skeleton = Skeleton::new();
     let already_spawned_nodes = HashMap::new();
@@ -143,9 +160,15 @@ pub fn spawn_nif_scenes(
         target_skeleton_id_opt,
         is_main_skeleton,
         asset_server: &asset_server,
+        colorless_meshes: &mut colorless_meshes,
+        images: &mut images,
+        sampled_images: &mut sampled_images,
         already_spawned_nodes,
         nif_node_index,
         ninodes_with_bvs,
+        listed_effects: HashSet::new(),
+        spawned_effects: HashMap::new(),
+        texture_effects: Vec::new(),
     };
     // spawn all the root nodes, parenting to the root entity
     for (index, current_node) in nif.roots.iter().enumerate() {
@@ -168,11 +191,13 @@ pub fn spawn_nif_scenes(
             &mut skeleton,
             &mut skeleton_map_res,
             &mut materials,
+            &mut fixed_function_materials,
             &mut meshes,
             &mut inverse_bindposes,
             &mut commands,
         );
     }
+    place_nif_effects(nif, &mut spawn_context, work_root, &mut commands);
     // If any of the nodes had bounding volumes, attach a component with the volumes
     // so the user can set up physics objects for them
     if spawn_context.ninodes_with_bvs.len() > 0 {
@@ -223,10 +248,19 @@ struct SpawnContext<'a> {
     target_skeleton_id_opt: Option<u64>,
     is_main_skeleton: bool,
     asset_server: &'a AssetServer,
+    colorless_meshes: &'a mut NifColorlessMeshes,
+    images: &'a mut Assets<Image>,
+    sampled_images: &'a mut NifSampledImages,
     /// In case of a circular dependency
     already_spawned_nodes: HashMap<NiKey, Entity>,
     nif_node_index: NifNodeIndex,
     ninodes_with_bvs: Vec<(Entity, NiKey)>,
+    /// Dynamic effects listed in the effects of any node
+    listed_effects: HashSet<NiKey>,
+    /// Light or projected texture key -> the spawned entity
+    spawned_effects: HashMap<NiKey, Entity>,
+    /// Texture effects listed by the nodes above the current one, innermost last
+    texture_effects: Vec<NiKey>,
 }
 fn spawn_nif_node_recursive<'a>(
     nif: &Nif,
@@ -236,7 +270,8 @@ fn spawn_nif_node_recursive<'a>(
     parent_bone_name_opt: Option<&str>,
     skeleton: &mut Skeleton,
     skeleton_map: &mut ResMut<SkeletonMap>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
+    materials: &mut ResMut<Assets<NifMaterial>>,
+    fixed_function_materials: &mut ResMut<Assets<NifFixedFunctionMaterial>>,
     meshes: &mut ResMut<Assets<Mesh>>,
     inverse_bindposes: &mut ResMut<Assets<SkinnedMeshInverseBindposes>>,
     commands: &mut Commands,
@@ -252,7 +287,10 @@ fn spawn_nif_node_recursive<'a>(
         return;
     };
     match ni_type {
-        NiType::NiNode(ni_node) => {
+        NiType::NiNode(_) | NiType::NiBillboardNode(_) => {
+            let Ok(ni_node) = <&NiNode>::try_from(ni_type) else {
+                return;
+            };
             let bevy_transform = Transform {
                 translation: ni_node.translation,
                 rotation: Quat::from_mat3(&ni_node.rotation.transpose()),
@@ -266,10 +304,20 @@ fn spawn_nif_node_recursive<'a>(
                     Visibility::Inherited,
                 ))
                 .id();
+            if nif.settings.is_hidden_node(&ni_node.name) {
+                commands
+                    .entity(new_ninode_entity)
+                    .insert(Visibility::Hidden);
+            }
             spawn_context
                 .nif_node_index
                 .named_nodes
                 .insert(hash_str(&ni_node.name), new_ninode_entity);
+            if let NiType::NiBillboardNode(billboard_node) = ni_type {
+                commands.entity(new_ninode_entity).insert(NifBillboard {
+                    mode: billboard_node.billboard_mode(),
+                });
+            }
             if let Some(_bounding_volume) = &ni_node.bounding_volume {
                 // dbg!("pushing bv to", new_ninode_entity);
                 spawn_context
@@ -280,6 +328,13 @@ fn spawn_nif_node_recursive<'a>(
             spawn_context
                 .already_spawned_nodes
                 .insert(current_key, new_ninode_entity);
+            let outer_texture_effects = spawn_context.texture_effects.len();
+            for effect in &ni_node.effects {
+                spawn_context.listed_effects.insert(effect.key);
+                if let Some(NiType::NiTextureEffect(_)) = nif.objects.get(effect.key) {
+                    spawn_context.texture_effects.push(effect.key);
+                }
+            }
             let mut current_bone_name_opt = None;
             if spawn_context.is_main_skeleton {
                 let formatted_name = format!("skeleton {}", ni_node.name);
@@ -304,11 +359,15 @@ fn spawn_nif_node_recursive<'a>(
                     skeleton,
                     skeleton_map,
                     materials,
+                    fixed_function_materials,
                     meshes,
                     inverse_bindposes,
                     commands,
                 );
             }
+            spawn_context
+                .texture_effects
+                .truncate(outer_texture_effects);
         }
         NiType::NiTriShape(ni_trishape) => {
             let nif_transform = ni_trishape;
@@ -328,10 +387,7 @@ fn spawn_nif_node_recursive<'a>(
             let name_ref: &str = &ni_trishape.name;
             let formatted_name = format!("NiTriShape: {:?}", name_ref);
             // Make shadow invisible (or if it's the main skeleton, the bones)
-            if name_ref == "Tri Shadow"
-                || name_ref == "Tri QuadPatch01"
-                || spawn_context.is_main_skeleton
-            {
+            if nif.settings.is_hidden_node(name_ref) || spawn_context.is_main_skeleton {
                 commands
                     .entity(new_nitrishape_entity)
                     .insert((Visibility::Hidden, Name::new("hidden")));
@@ -349,18 +405,26 @@ fn spawn_nif_node_recursive<'a>(
                 return;
             };
 
-            let mesh_handle = match consumed_ni_type {
-                ConsumedNiType::NiTriShapeData(mesh_handle) => {
-                    commands
-                        .entity(new_nitrishape_entity)
-                        .insert(Mesh3d(mesh_handle.clone()));
-                    mesh_handle
-                }
+            let ConsumedNiType::NiTriShapeData(mesh_handle) = consumed_ni_type else {
+                warn!("NiTriShape geometry isn't a mesh!");
+                return;
             };
+            let mut mesh_handle = mesh_handle.clone();
             // Loop through properties such as material and textures
             let ni_properties = &ni_trishape.properties;
-            let mut material_opt: Option<StandardMaterial> = None;
+            let mut mat_prop_opt = None;
             let mut texture_handle_opt = None;
+            let mut apply_mode = ApplyMode::default();
+            // Fixed function defaults, specular off and smooth shading
+            let mut specular = false;
+            let mut smooth = true;
+            let mut alpha_mode = AlphaMode::Opaque;
+            let mut zero_alpha = false;
+            let mut vertex_color_prop_opt = None;
+            // NetImmerse draws only counter clockwise faces unless told otherwise
+            let mut cull_mode = Some(Face::Back);
+            let mut extension = NifMaterialExtension::default();
+            let mut wireframe = false;
             for property in ni_properties {
                 if let Some(ni_property) = nif.objects.get(property.key) {
                     match ni_property {
@@ -369,26 +433,131 @@ fn spawn_nif_node_recursive<'a>(
                                 tex_prop,
                                 nif,
                                 spawn_context.asset_server,
+                                &mut *spawn_context.images,
+                                &mut *spawn_context.sampled_images,
+                                &mut extension,
                             );
+                            apply_mode = tex_prop.apply_mode;
                         }
                         NiType::NiMaterialProperty(mat_prop) => {
-                            material_opt = Some(process_nimaterialproperty(mat_prop));
+                            mat_prop_opt = Some(mat_prop);
+                        }
+                        NiType::NiSpecularProperty(specular_prop) => {
+                            specular = specular_prop.specular();
+                        }
+                        NiType::NiShadeProperty(shade_prop) => {
+                            smooth = shade_prop.smooth();
+                        }
+                        NiType::NiAlphaProperty(alpha_prop) => {
+                            (alpha_mode, zero_alpha) = process_nialphaproperty(alpha_prop);
+                        }
+                        NiType::NiVertexColorProperty(vertex_color_prop) => {
+                            vertex_color_prop_opt = Some(vertex_color_prop);
+                        }
+                        NiType::NiStencilProperty(stencil_prop) => {
+                            cull_mode = process_nistencilproperty(stencil_prop);
+                        }
+                        NiType::NiZBufferProperty(zbuffer_prop) => {
+                            process_nizbufferproperty(zbuffer_prop, &mut extension);
+                        }
+                        NiType::NiWireframeProperty(wireframe_prop) => {
+                            wireframe = wireframe_prop.wireframe();
                         }
                         _ => {}
                     }
                 }
             }
+            // The innermost environment map wins
+            for effect_key in spawn_context.texture_effects.iter().rev() {
+                if let Some(NiType::NiTextureEffect(texture_effect)) = nif.objects.get(*effect_key)
+                    && process_nitextureeffect(
+                        texture_effect,
+                        nif,
+                        spawn_context.asset_server,
+                        &mut *spawn_context.images,
+                        &mut *spawn_context.sampled_images,
+                        &mut extension,
+                    )
+                {
+                    break;
+                }
+            }
+            // Without an NiVertexColorProperty vertex colors tint the ambient and diffuse color
+            let mut use_vertex_colors = true;
             // Assemble the final material, if there was one
-            if let Some(mut material) = material_opt {
-                material.base_color_texture = texture_handle_opt.take();
-                material.alpha_mode = AlphaMode::Mask(1.0);
-                //TODO:: fix culling, when attaching bones some transform scales are set to -1
-                //which breaks the culling, for now no culling
-                material.cull_mode = None;
-                let material_h = materials.add(material);
-                commands
-                    .entity(new_nitrishape_entity)
-                    .insert(MeshMaterial3d(material_h));
+            if let Some(mat_prop) = mat_prop_opt {
+                match nif.settings.shading {
+                    NifShading::Pbr => {
+                        let mut material = process_nimaterialproperty(mat_prop);
+                        if let Some((base_texture, uv_channel)) = texture_handle_opt.take() {
+                            material.base_color_texture = Some(base_texture);
+                            material.base_color_channel = uv_channel;
+                        }
+                        material.alpha_mode = alpha_mode;
+                        if zero_alpha {
+                            material.base_color.set_alpha(0.0);
+                        }
+                        if let Some(vertex_color_prop) = vertex_color_prop_opt {
+                            use_vertex_colors =
+                                process_nivertexcolorproperty(vertex_color_prop, &mut material);
+                        }
+                        // Mirrored attachments swap this around in attach_parts
+                        material.cull_mode = cull_mode;
+                        material.double_sided = cull_mode.is_none();
+                        let material_h = materials.add(NifMaterial {
+                            base: material,
+                            extension,
+                        });
+                        commands
+                            .entity(new_nitrishape_entity)
+                            .insert(MeshMaterial3d(material_h));
+                    }
+                    NifShading::FixedFunction => {
+                        let mut material = process_fixed_function_material(mat_prop, extension);
+                        let uniform = &mut material.uniform;
+                        if let Some((base_texture, uv_channel)) = texture_handle_opt.take() {
+                            uniform.flags |= NIF_FIXED_FUNCTION_BASE_TEXTURE;
+                            if uv_channel == UvChannel::Uv1 {
+                                uniform.flags |= NIF_FIXED_FUNCTION_BASE_UV_1;
+                            }
+                            material.base_texture = Some(base_texture);
+                        }
+                        uniform.apply_mode = apply_mode as u32;
+                        if specular {
+                            uniform.flags |= NIF_FIXED_FUNCTION_SPECULAR;
+                        }
+                        if smooth {
+                            uniform.flags |= NIF_FIXED_FUNCTION_SMOOTH;
+                        }
+                        if zero_alpha {
+                            uniform.diffuse.w = 0.0;
+                        }
+                        if let AlphaMode::Mask(cutoff) = alpha_mode {
+                            uniform.flags |= NIF_FIXED_FUNCTION_ALPHA_MASK;
+                            uniform.alpha_cutoff = cutoff;
+                        }
+                        use_vertex_colors =
+                            process_fixed_function_vertex_colors(vertex_color_prop_opt, uniform);
+                        material.alpha_mode = alpha_mode;
+                        material.cull_mode = cull_mode;
+                        let material_h = fixed_function_materials.add(material);
+                        commands
+                            .entity(new_nitrishape_entity)
+                            .insert(MeshMaterial3d(material_h));
+                    }
+                }
+            }
+            if !use_vertex_colors {
+                mesh_handle = spawn_context
+                    .colorless_meshes
+                    .without_vertex_colors(mesh_handle, meshes);
+            }
+            commands
+                .entity(new_nitrishape_entity)
+                .insert(Mesh3d(mesh_handle.clone()));
+            if wireframe {
+                // Only drawn when the app has the WireframePlugin
+                commands.entity(new_nitrishape_entity).insert(Wireframe);
             }
             commands
                 .entity(parent_entity)
@@ -403,7 +572,7 @@ fn spawn_nif_node_recursive<'a>(
                             skin_instance,
                             new_nitrishape_entity,
                             skeleton_map,
-                            mesh_handle,
+                            &mesh_handle,
                             meshes,
                             inverse_bindposes,
                             commands,
@@ -413,10 +582,131 @@ fn spawn_nif_node_recursive<'a>(
                 }
             }
         }
+        _ if is_nif_light(ni_type) || is_nif_projected_texture(ni_type) => {
+            if let Some(effect_entity) =
+                spawn_nif_effect(nif, spawn_context, ni_type, parent_entity, commands)
+            {
+                spawn_context
+                    .already_spawned_nodes
+                    .insert(current_key, effect_entity);
+                spawn_context
+                    .spawned_effects
+                    .insert(current_key, effect_entity);
+            }
+        }
         _ => {}
     }
 }
 
+/// Spawn a light or a projected texture effect under `parent_entity`
+fn spawn_nif_effect(
+    nif: &Nif,
+    spawn_context: &mut SpawnContext,
+    ni_type: &NiType,
+    parent_entity: Entity,
+    commands: &mut Commands,
+) -> Option<Entity> {
+    if is_nif_light(ni_type) {
+        return spawn_nif_light(ni_type, parent_entity, commands);
+    }
+    spawn_nif_projected_texture(
+        ni_type,
+        nif,
+        parent_entity,
+        spawn_context.asset_server,
+        &mut *spawn_context.images,
+        &mut *spawn_context.sampled_images,
+        commands,
+    )
+}
+
+/// Spawn the lights and projected textures that only appear in an effects list, and hide the
+/// ones no node lists. Listed-only effects have no parent node, so they go under the scene root.
+/// An effect no node lists affects nothing in NetImmerse.
+fn place_nif_effects(
+    nif: &Nif,
+    spawn_context: &mut SpawnContext,
+    scene_root: Entity,
+    commands: &mut Commands,
+) {
+    let listed_effects = std::mem::take(&mut spawn_context.listed_effects);
+    for effect_key in &listed_effects {
+        if spawn_context.spawned_effects.contains_key(effect_key) {
+            continue;
+        }
+        let Some(ni_type) = nif.objects.get(*effect_key) else {
+            continue;
+        };
+        if !is_nif_light(ni_type) && !is_nif_projected_texture(ni_type) {
+            continue;
+        }
+        if let Some(effect_entity) =
+            spawn_nif_effect(nif, spawn_context, ni_type, scene_root, commands)
+        {
+            spawn_context
+                .already_spawned_nodes
+                .insert(*effect_key, effect_entity);
+            spawn_context
+                .spawned_effects
+                .insert(*effect_key, effect_entity);
+        }
+    }
+    for (effect_key, effect_entity) in &spawn_context.spawned_effects {
+        if !listed_effects.contains(effect_key) {
+            commands.entity(*effect_entity).insert(Visibility::Hidden);
+        }
+    }
+    spawn_context.listed_effects = listed_effects;
+}
+
+/// Copies of NIF meshes without their vertex colors, for shapes whose NiVertexColorProperty
+/// ignores them. The mesh data is shared between every shape using it, so each source mesh gets
+/// one copy shared by all of those shapes.
+#[derive(Resource, Default)]
+pub struct NifColorlessMeshes {
+    meshes: HashMap<AssetId<Mesh>, Handle<Mesh>>,
+}
+
+impl NifColorlessMeshes {
+    fn without_vertex_colors(
+        &mut self,
+        mesh_handle: Handle<Mesh>,
+        meshes: &mut Assets<Mesh>,
+    ) -> Handle<Mesh> {
+        if let Some(colorless) = self.meshes.get(&mesh_handle.id()) {
+            return colorless.clone();
+        }
+        let Some(mesh) = meshes.get(&mesh_handle) else {
+            return mesh_handle;
+        };
+        match mesh.try_attribute_option(Mesh::ATTRIBUTE_COLOR) {
+            Ok(Some(_)) => {}
+            Ok(None) => return mesh_handle,
+            Err(error) => {
+                warn!("Could not remove vertex colors: {error}");
+                return mesh_handle;
+            }
+        }
+        let mut mesh = mesh.clone();
+        let _ = mesh.try_remove_attribute(Mesh::ATTRIBUTE_COLOR);
+        let colorless = meshes.add(mesh);
+        self.meshes.insert(mesh_handle.id(), colorless.clone());
+        colorless
+    }
+}
+
+/// Drops the colorless copies of meshes that are no longer used.
+pub fn prune_nif_colorless_meshes(
+    mut events: MessageReader<AssetEvent<Mesh>>,
+    mut colorless_meshes: ResMut<NifColorlessMeshes>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Unused { id } | AssetEvent::Removed { id } = event {
+            colorless_meshes.meshes.remove(id);
+        }
+    }
+}
+
 /// Apply Skinning Attributes
 fn apply_skin_instance(
     nif: &Nif,
@@ -445,8 +735,14 @@ fn apply_skin_instance(
     // 1. Add vertex attributes
     if let Some(mut mesh) = meshes.get_mut(mesh_handle) {
         // Only insert joint indices/weights if some other node didn't already do it for this mesh
-        if mesh.attribute(Mesh::ATTRIBUTE_JOINT_INDEX).is_none() {
-            if let Some(vertex_count) = mesh.attribute(Mesh::ATTRIBUTE_POSITION).map(|a| a.len()) {
+        if mesh
+            .try_attribute_option(Mesh::ATTRIBUTE_JOINT_INDEX)
+            .is_ok_and(|joint_index| joint_index.is_none())
+        {
+            if let Ok(Some(vertex_count)) = mesh
+                .try_attribute_option(Mesh::ATTRIBUTE_POSITION)
+                .map(|a| a.map(VertexAttributeValues::len))
+            {
                 // The 4 indices of the bones that affect this vertex, like [bone1_idx, bone2_idx,..]
                 let mut joint_indices: Vec<[u16; 4]> = vec![[0, 0, 0, 0]; vertex_count];
                 // How much each bone in the above list pulls this vertex, should sum to 1.0
@@ -485,17 +781,23 @@ fn apply_skin_instance(
                     }
                 }
                 // Insert Bevy vertex attributes
-                mesh.insert_attribute(
-                    Mesh::ATTRIBUTE_JOINT_INDEX,
-                    VertexAttributeValues::Uint16x4(joint_indices),
-                );
-                mesh.insert_attribute(
-                    Mesh::ATTRIBUTE_JOINT_WEIGHT,
-                    VertexAttributeValues::Float32x4(joint_weights),
-                );
+                if let Err(error) = mesh
+                    .try_insert_attribute(
+                        Mesh::ATTRIBUTE_JOINT_INDEX,
+                        VertexAttributeValues::Uint16x4(joint_indices),
+                    )
+                    .and_then(|()| {
+                        mesh.try_insert_attribute(
+                            Mesh::ATTRIBUTE_JOINT_WEIGHT,
+                            VertexAttributeValues::Float32x4(joint_weights),
+                        )
+                    })
+                {
+                    warn!("   Could not apply skinning attributes: {error}");
+                }
             } else {
                 warn!(
-                    "   Could not apply skinning attributes: Mesh for {:?} missing positions?",
+                    "   Could not apply skinning attributes: Mesh for {:?} missing positions or not on the CPU?",
                     skin_instance.root.key,
                 );
             }
@@ -1,69 +1,386 @@
-use bevy_asset::{AssetServer, Handle};
-use bevy_color::{Color, LinearRgba};
+use bevy_asset::{AssetServer, Assets, Handle};
+use bevy_color::{Color, ColorToComponents, LinearRgba};
 use bevy_image::Image;
+use bevy_log::warn;
 use bevy_material::AlphaMode;
+use bevy_math::{Vec3, Vec4};
+use bevy_mesh::UvChannel;
 use bevy_pbr::StandardMaterial;
+use bevy_render::render_resource::{CompareFunction, Face};
 use nif::{
-    NiMaterialProperty, NiTexturingProperty, NiType, TextureMap, TextureSource, loader::Nif,
+    AlphaBlendFunction, AlphaTestFunction, CoordGenType, DrawMode, LightingMode, Map,
+    NiAlphaProperty, NiLink, NiMaterialProperty, NiSourceTexture, NiStencilProperty,
+    NiTextureEffect, NiTexturingProperty, NiType, NiVertexColorProperty, NiZBufferProperty,
+    SourceVertexMode, TextureMap, TextureSource, TextureType, ZBufferTestFunction,
+    loader::{ConsumedNiType, Nif},
 };
 
 use crate::helper_funcs::resolve_nif_path;
+use crate::material::{
+    NIF_FIXED_FUNCTION_EMISSIVE_ONLY, NIF_FIXED_FUNCTION_VERTEX_AMBIENT_DIFFUSE,
+    NIF_FIXED_FUNCTION_VERTEX_EMISSIVE, NIF_TEXTURE_BUMP, NIF_TEXTURE_DARK, NIF_TEXTURE_DECAL,
+    NIF_TEXTURE_DETAIL, NIF_TEXTURE_ENV, NIF_TEXTURE_GLOSS, NIF_TEXTURE_GLOW,
+    NifFixedFunctionMaterial, NifFixedFunctionUniform, NifMaterialExtension,
+};
+use crate::sampler::NifSampledImages;
+
+/// NiTexturingProperty slot order, every slot from the decal slot on is a decal
+const BASE_SLOT: usize = 0;
+const DARK_SLOT: usize = 1;
+const DETAIL_SLOT: usize = 2;
+const GLOSS_SLOT: usize = 3;
+const GLOW_SLOT: usize = 4;
+const BUMP_SLOT: usize = 5;
+const DECAL_SLOT: usize = 6;
 
+/// Load every texture slot of an NiTexturingProperty. The extra slots go into the material
+/// extension, the base texture is returned with the UV channel it reads.
 pub fn process_nitexturingproperty(
     tex_prop: &NiTexturingProperty,
     nif: &Nif,
     asset_server: &AssetServer,
-) -> Option<Handle<Image>> {
-    let mut texture_handle_opt: Option<Handle<Image>> = None;
-    let base_texture = tex_prop.texture_maps[0].as_ref().unwrap();
-    match base_texture {
-        TextureMap::Map(tex_map) => {
-            if let Some(tex_ni_type) = nif.objects.get(tex_map.texture.key) {
-                match tex_ni_type {
-                    NiType::NiSourceTexture(source_texture) => {
-                        match &source_texture.source {
-                            TextureSource::External(ext_path) => {
-                                //dbg!("loading");
-                                //dbg!(ext_path);
-                                let path = resolve_nif_path(ext_path);
-                                if let Some(path) = path {
-                                    texture_handle_opt = Some(asset_server.load(path));
-                                }
-                            }
-                            TextureSource::Internal(_link) => {
-                                dbg!("Unimplemented!");
-                                //TODO::
-                            }
-                        }
-                    }
-                    _ => {}
+    images: &mut Assets<Image>,
+    sampled_images: &mut NifSampledImages,
+    extension: &mut NifMaterialExtension,
+) -> Option<(Handle<Image>, UvChannel)> {
+    let mut base_texture_opt = None;
+    for (slot, texture_map) in tex_prop.texture_maps.iter().enumerate() {
+        let Some(texture_map) = texture_map else {
+            continue;
+        };
+        let map = match texture_map {
+            TextureMap::Map(map) => map,
+            TextureMap::BumpMap(bump_map) => &bump_map.base,
+        };
+        let Some(image) = load_texture_map(map, nif, asset_server) else {
+            continue;
+        };
+        let image = sampled_images.sampled(images, image, map.clamp_mode, map.filter_mode);
+        let uv_set = texture_uv_set(map);
+        let settings = &mut extension.texture_settings;
+        match slot {
+            BASE_SLOT => {
+                let uv_channel = if uv_set == 1 {
+                    UvChannel::Uv1
+                } else {
+                    UvChannel::Uv0
+                };
+                base_texture_opt = Some((image, uv_channel));
+            }
+            DARK_SLOT => {
+                settings.flags |= NIF_TEXTURE_DARK;
+                settings.dark_uv_set = uv_set;
+                extension.dark_texture = Some(image);
+            }
+            DETAIL_SLOT => {
+                settings.flags |= NIF_TEXTURE_DETAIL;
+                settings.detail_uv_set = uv_set;
+                extension.detail_texture = Some(image);
+            }
+            GLOSS_SLOT => {
+                settings.flags |= NIF_TEXTURE_GLOSS;
+                settings.gloss_uv_set = uv_set;
+                extension.gloss_texture = Some(image);
+            }
+            GLOW_SLOT => {
+                settings.flags |= NIF_TEXTURE_GLOW;
+                settings.glow_uv_set = uv_set;
+                extension.glow_texture = Some(image);
+            }
+            BUMP_SLOT => {
+                settings.flags |= NIF_TEXTURE_BUMP;
+                settings.bump_uv_set = uv_set;
+                if let TextureMap::BumpMap(bump_map) = texture_map {
+                    settings.bump_luma_scale = bump_map.luma_scale;
+                    settings.bump_luma_offset = bump_map.luma_offset;
+                    settings.bump_matrix = Vec4::from_array(bump_map.displacement.to_cols_array());
                 }
+                extension.bump_texture = Some(image);
+            }
+            // Only one decal layer is supported, the first one wins
+            _ if slot >= DECAL_SLOT && extension.decal_texture.is_none() => {
+                settings.flags |= NIF_TEXTURE_DECAL;
+                settings.decal_uv_set = uv_set;
+                extension.decal_texture = Some(image);
             }
+            _ => {}
         }
-        TextureMap::BumpMap(_tex_bumpmap) => {
-            dbg!("Unimplemented!");
+    }
+    base_texture_opt
+}
+/// Give a shape under an environment map NiTextureEffect the sphere map reflection in its
+/// material. Returns false for texture effects that aren't environment maps.
+pub fn process_nitextureeffect(
+    texture_effect: &NiTextureEffect,
+    nif: &Nif,
+    asset_server: &AssetServer,
+    images: &mut Assets<Image>,
+    sampled_images: &mut NifSampledImages,
+    extension: &mut NifMaterialExtension,
+) -> bool {
+    if texture_effect.texture_type != TextureType::EnvironmentMap {
+        return false;
+    }
+    if texture_effect.coordinate_generation_type != CoordGenType::SphereMap {
+        warn!(
+            "Environment map with {:?} coordinates, only SphereMap is supported",
+            texture_effect.coordinate_generation_type
+        );
+        return false;
+    }
+    let Some(image) = load_source_texture(&texture_effect.source_texture, nif, asset_server) else {
+        return false;
+    };
+    extension.texture_settings.flags |= NIF_TEXTURE_ENV;
+    extension.env_texture = Some(sampled_images.sampled(
+        images,
+        image,
+        texture_effect.texture_clamp,
+        texture_effect.texture_filter,
+    ));
+    true
+}
+/// Resolve the source texture of a texture slot to an image handle
+fn load_texture_map(map: &Map, nif: &Nif, asset_server: &AssetServer) -> Option<Handle<Image>> {
+    load_source_texture(&map.texture, nif, asset_server)
+}
+/// Resolve an NiSourceTexture to an image handle
+pub fn load_source_texture(
+    texture: &NiLink<NiSourceTexture>,
+    nif: &Nif,
+    asset_server: &AssetServer,
+) -> Option<Handle<Image>> {
+    let Some(NiType::NiSourceTexture(source_texture)) = nif.objects.get(texture.key) else {
+        return None;
+    };
+    match &source_texture.source {
+        TextureSource::External(ext_path) => resolve_nif_path(ext_path, &nif.settings.texture_root)
+            .map(|path| asset_server.load(path)),
+        TextureSource::Internal(link) => match nif.block_assets.get(&link.key) {
+            Some(ConsumedNiType::NiPixelData(image)) => Some(image.clone()),
+            _ => None,
+        },
+    }
+}
+/// Bevy meshes only carry two UV sets, slots reading any other set fall back to the first
+fn texture_uv_set(map: &Map) -> u32 {
+    match map.texture_index {
+        0 | 1 => map.texture_index as u32,
+        index => {
+            warn!("Texture reads UV set {index}, only sets 0 and 1 are supported");
+            0
         }
     }
-    texture_handle_opt
 }
 pub fn process_nimaterialproperty(mat_prop: &NiMaterialProperty) -> StandardMaterial {
-    let mut material = StandardMaterial::default();
-    material.base_color = Color::srgb(
-        mat_prop.diffuse_color[0],
-        mat_prop.diffuse_color[1],
-        mat_prop.diffuse_color[2],
-    );
-    material.emissive = LinearRgba::rgb(
-        mat_prop.emissive_color[0],
-        mat_prop.emissive_color[1],
-        mat_prop.emissive_color[2],
-    );
-    material.metallic = 0.1;
-    material.perceptual_roughness = 1.0 - (mat_prop.shine / 100.0).clamp(0.0, 1.0);
-    material.alpha_mode = if mat_prop.alpha < 0.99 {
-        AlphaMode::Blend
-    } else {
-        AlphaMode::Opaque
+    StandardMaterial {
+        base_color: Color::srgba(
+            mat_prop.diffuse_color[0],
+            mat_prop.diffuse_color[1],
+            mat_prop.diffuse_color[2],
+            mat_prop.alpha,
+        ),
+        emissive: LinearRgba::rgb(
+            mat_prop.emissive_color[0],
+            mat_prop.emissive_color[1],
+            mat_prop.emissive_color[2],
+        ),
+        metallic: 0.1,
+        perceptual_roughness: 1.0 - (mat_prop.shine / 100.0).clamp(0.0, 1.0),
+        // Without an NiAlphaProperty the material alpha is ignored, see process_nialphaproperty
+        alpha_mode: AlphaMode::Opaque,
+        ..Default::default()
+    }
+}
+/// Translate the blend and test state of an NiAlphaProperty into the closest Bevy alpha mode.
+/// Bevy can't blend and alpha test at the same time, so blending wins when both are enabled.
+///
+/// The returned flag is set when the material alpha has to be zeroed: premultiplied blending
+/// with a zero alpha is `src + dst`, the only way to add a colour without scaling it by alpha.
+pub fn process_nialphaproperty(alpha_prop: &NiAlphaProperty) -> (AlphaMode, bool) {
+    if alpha_prop.alpha_blending() {
+        use AlphaBlendFunction::*;
+        return match (alpha_prop.src_blend_mode(), alpha_prop.dst_blend_mode()) {
+            (SrcAlpha, InvSrcAlpha) => (AlphaMode::Blend, false),
+            (One, InvSrcAlpha) => (AlphaMode::Premultiplied, false),
+            // Glow effects, Bevy's additive mode already scales the colour by its alpha
+            (SrcAlpha, One) => (AlphaMode::Add, false),
+            (One, One) => (AlphaMode::Premultiplied, true),
+            // Both multiply the framebuffer by the incoming colour
+            (DstColor, Zero) | (Zero, SrcColor) => (AlphaMode::Multiply, false),
+            _ => (AlphaMode::Blend, false),
+        };
+    }
+    if alpha_prop.alpha_testing() {
+        let cutoff = f32::from(alpha_prop.test_ref) / 255.0;
+        // Bevy only discards below the cutoff, which covers the common Greater/GreaterEqual
+        // tests used for foliage and other cutouts
+        let alpha_mode = match alpha_prop.test_mode() {
+            AlphaTestFunction::Always => AlphaMode::Opaque,
+            AlphaTestFunction::Never => AlphaMode::Mask(f32::INFINITY),
+            _ => AlphaMode::Mask(cutoff),
+        };
+        return (alpha_mode, false);
+    }
+    (AlphaMode::Opaque, false)
+}
+/// Apply an NiVertexColorProperty to the material, returns whether the mesh vertex colors
+/// should be kept.
+/// StandardMaterial always multiplies the base color by the vertex colors and has no per vertex
+/// emissive, so emissive vertex colors are approximated by drawing the shape unlit.
+pub fn process_nivertexcolorproperty(
+    vertex_color_prop: &NiVertexColorProperty,
+    material: &mut StandardMaterial,
+) -> bool {
+    // Only the emissive term is lit, which is what unlit means for a NIF material
+    if vertex_color_prop.lighting_mode == LightingMode::Emissive {
+        material.unlit = true;
+    }
+    match vertex_color_prop.source_vertex_mode {
+        SourceVertexMode::Ignore => false,
+        SourceVertexMode::Emissive => {
+            material.unlit = true;
+            true
+        }
+        SourceVertexMode::AmbientDiffuse => true,
+    }
+}
+/// The faces an NiStencilProperty draws, as the face Bevy should cull.
+pub fn process_nistencilproperty(stencil_prop: &NiStencilProperty) -> Option<Face> {
+    match stencil_prop.draw_mode {
+        DrawMode::Default | DrawMode::CounterClockwise => Some(Face::Back),
+        DrawMode::Clockwise => Some(Face::Front),
+        DrawMode::Both => None,
+    }
+}
+/// Copy the depth state of an NiZBufferProperty onto the material.
+/// Files before 4.1.0.12 don't store a test function and always test less or equal.
+pub fn process_nizbufferproperty(
+    zbuffer_prop: &NiZBufferProperty,
+    extension: &mut NifMaterialExtension,
+) {
+    extension.depth_test = zbuffer_prop.z_buffer_test();
+    extension.depth_compare = depth_compare(zbuffer_prop.function);
+    extension.depth_write = zbuffer_prop.z_buffer_write();
+}
+/// The compare function of a NIF depth test. NetImmerse depth grows away from the camera while
+/// Bevy's reverse-Z depth shrinks, so every ordered comparison is turned around.
+fn depth_compare(function: ZBufferTestFunction) -> CompareFunction {
+    match function {
+        ZBufferTestFunction::Always => CompareFunction::Always,
+        ZBufferTestFunction::Less => CompareFunction::Greater,
+        ZBufferTestFunction::Equal => CompareFunction::Equal,
+        ZBufferTestFunction::LessEqual => CompareFunction::GreaterEqual,
+        ZBufferTestFunction::Greater => CompareFunction::Less,
+        ZBufferTestFunction::NotEqual => CompareFunction::NotEqual,
+        ZBufferTestFunction::GreaterEqual => CompareFunction::LessEqual,
+        ZBufferTestFunction::Never => CompareFunction::Never,
+    }
+}
+/// Pick which material colors the vertex colors replace, returns whether the mesh vertex colors
+/// should be kept. Without an NiVertexColorProperty they replace ambient and diffuse.
+pub fn process_fixed_function_vertex_colors(
+    vertex_color_prop_opt: Option<&NiVertexColorProperty>,
+    uniform: &mut NifFixedFunctionUniform,
+) -> bool {
+    let Some(vertex_color_prop) = vertex_color_prop_opt else {
+        uniform.flags |= NIF_FIXED_FUNCTION_VERTEX_AMBIENT_DIFFUSE;
+        return true;
+    };
+    if vertex_color_prop.lighting_mode == LightingMode::Emissive {
+        uniform.flags |= NIF_FIXED_FUNCTION_EMISSIVE_ONLY;
+    }
+    match vertex_color_prop.source_vertex_mode {
+        SourceVertexMode::Ignore => false,
+        SourceVertexMode::Emissive => {
+            uniform.flags |= NIF_FIXED_FUNCTION_VERTEX_EMISSIVE;
+            true
+        }
+        SourceVertexMode::AmbientDiffuse => {
+            uniform.flags |= NIF_FIXED_FUNCTION_VERTEX_AMBIENT_DIFFUSE;
+            true
+        }
+    }
+}
+/// Build the fixed function material from an NiMaterialProperty, taking over the texture slots
+/// and depth state gathered in the material extension.
+pub fn process_fixed_function_material(
+    mat_prop: &NiMaterialProperty,
+    extension: NifMaterialExtension,
+) -> NifFixedFunctionMaterial {
+    let linear = |color: Vec3| Color::srgb(color.x, color.y, color.z).to_linear().to_vec3();
+    let mut material = NifFixedFunctionMaterial::from_extension(extension);
+    material.uniform = NifFixedFunctionUniform {
+        ambient: linear(mat_prop.ambient_color).extend(1.0),
+        diffuse: linear(mat_prop.diffuse_color).extend(mat_prop.alpha),
+        specular: linear(mat_prop.specular_color).extend(1.0),
+        emissive: linear(mat_prop.emissive_color).extend(1.0),
+        shine: mat_prop.shine,
+        ..Default::default()
     };
     material
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blending(src: AlphaBlendFunction, dst: AlphaBlendFunction) -> NiAlphaProperty {
+        let mut alpha_prop = NiAlphaProperty::default();
+        alpha_prop.set_alpha_blending(true);
+        alpha_prop.set_src_blend_mode(src);
+        alpha_prop.set_dst_blend_mode(dst);
+        alpha_prop
+    }
+
+    #[test]
+    fn test_alpha_blending() {
+        use AlphaBlendFunction::*;
+        let mode = |src, dst| process_nialphaproperty(&blending(src, dst));
+        assert_eq!(mode(SrcAlpha, InvSrcAlpha), (AlphaMode::Blend, false));
+        assert_eq!(mode(One, InvSrcAlpha), (AlphaMode::Premultiplied, false));
+        assert_eq!(mode(SrcAlpha, One), (AlphaMode::Add, false));
+        assert_eq!(mode(One, One), (AlphaMode::Premultiplied, true));
+        assert_eq!(mode(DstColor, Zero), (AlphaMode::Multiply, false));
+        assert_eq!(mode(Zero, SrcColor), (AlphaMode::Multiply, false));
+        assert_eq!(mode(InvDstColor, DstAlpha), (AlphaMode::Blend, false));
+    }
+
+    #[test]
+    fn test_alpha_testing() {
+        let mut alpha_prop = NiAlphaProperty::default();
+        assert_eq!(process_nialphaproperty(&alpha_prop).0, AlphaMode::Opaque);
+        alpha_prop.set_alpha_testing(true);
+        alpha_prop.set_test_mode(AlphaTestFunction::Greater);
+        alpha_prop.test_ref = 255;
+        assert_eq!(process_nialphaproperty(&alpha_prop).0, AlphaMode::Mask(1.0));
+        alpha_prop.set_test_mode(AlphaTestFunction::Never);
+        assert_eq!(
+            process_nialphaproperty(&alpha_prop).0,
+            AlphaMode::Mask(f32::INFINITY)
+        );
+        // Blending wins over testing
+        alpha_prop.set_alpha_blending(true);
+        alpha_prop.set_src_blend_mode(AlphaBlendFunction::SrcAlpha);
+        alpha_prop.set_dst_blend_mode(AlphaBlendFunction::InvSrcAlpha);
+        assert_eq!(process_nialphaproperty(&alpha_prop).0, AlphaMode::Blend);
+    }
+
+    #[test]
+    fn test_depth_compare() {
+        let mut zbuffer_prop = NiZBufferProperty::default();
+        zbuffer_prop.base.flags = 0x0003;
+        let mut extension = NifMaterialExtension::default();
+        process_nizbufferproperty(&zbuffer_prop, &mut extension);
+        assert!(extension.depth_test && extension.depth_write);
+        // The default less or equal test is Bevy's own depth test
+        assert_eq!(extension.depth_compare, CompareFunction::GreaterEqual);
+
+        zbuffer_prop.function = ZBufferTestFunction::Less;
+        process_nizbufferproperty(&zbuffer_prop, &mut extension);
+        assert_eq!(extension.depth_compare, CompareFunction::Greater);
+        zbuffer_prop.function = ZBufferTestFunction::Equal;
+        process_nizbufferproperty(&zbuffer_prop, &mut extension);
+        assert_eq!(extension.depth_compare, CompareFunction::Equal);
+    }
+}
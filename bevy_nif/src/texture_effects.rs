@@ -0,0 +1,171 @@
+//! Conversion of `NiTextureEffect` blocks.
+//!
+//! A texture effect projects its source texture onto the geometry under the nodes that list it
+//! in `NiNode::effects`, and `texture_type` says what the projected texture means:
+//!
+//! - **environment map**: a sphere map reflected by the surface, Morrowind's glass and ebony
+//!   shine. It isn't spawned, every affected shape gets it as the env texture of its material,
+//!   see `process_nitextureeffect`.
+//! - **projected light / shadow**: spawned here as a Bevy [`ClusteredDecal`]. Lights go through
+//!   the decal's emissive texture and shadows through its base color texture, which is alpha
+//!   blended over the surface. Clustered decals need bindless textures, so they don't show on
+//!   platforms without them.
+//! - **fog map**: not supported.
+//!
+//! NetImmerse projects parallel texture effects with `uv = projection_matrix * p +
+//! projection_translation` and doesn't bound them along the projection direction. A clustered
+//! decal is a unit cube projecting down its -Z axis, so the decal transform is the inverse of
+//! that mapping, [`NIF_PROJECTED_TEXTURE_DEPTH`] deep and centered on the effect.
+
+use crate::sampler::NifSampledImages;
+use crate::spawning_ni_helpers::load_source_texture;
+use bevy_asset::{AssetServer, Assets};
+use bevy_ecs::{entity::Entity, hierarchy::ChildOf, system::Commands};
+use bevy_image::Image;
+use bevy_light::ClusteredDecal;
+use bevy_log::warn;
+use bevy_math::{Mat4, Vec4};
+use bevy_transform::components::Transform;
+use nif::{CoordGenType, NiTextureEffect, NiType, TextureType, loader::Nif};
+
+/// How far a projected texture reaches along its projection direction, NetImmerse doesn't
+/// bound it at all.
+pub const NIF_PROJECTED_TEXTURE_DEPTH: f32 = 2048.0;
+
+/// Returns true if the `NiType` is a texture effect [`spawn_nif_projected_texture`] handles.
+pub fn is_nif_projected_texture(ni_type: &NiType) -> bool {
+    matches!(
+        ni_type,
+        NiType::NiTextureEffect(texture_effect)
+            if matches!(
+                texture_effect.texture_type,
+                TextureType::ProjectedLight | TextureType::ProjectedShadow
+            )
+    )
+}
+
+/// Spawn a projected light or shadow texture effect as a clustered decal under `parent_entity`.
+/// The projection is taken to be in the space of `parent_entity`.
+pub fn spawn_nif_projected_texture(
+    ni_type: &NiType,
+    nif: &Nif,
+    parent_entity: Entity,
+    asset_server: &AssetServer,
+    images: &mut Assets<Image>,
+    sampled_images: &mut NifSampledImages,
+    commands: &mut Commands,
+) -> Option<Entity> {
+    let NiType::NiTextureEffect(texture_effect) = ni_type else {
+        return None;
+    };
+    if texture_effect.coordinate_generation_type != CoordGenType::WorldParallel {
+        warn!(
+            "Projected texture effect with {:?} coordinates, only WorldParallel is supported",
+            texture_effect.coordinate_generation_type
+        );
+        return None;
+    }
+    let image = load_source_texture(&texture_effect.source_texture, nif, asset_server)?;
+    let image = sampled_images.sampled(
+        images,
+        image,
+        texture_effect.texture_clamp,
+        texture_effect.texture_filter,
+    );
+    let transform = projected_texture_transform(texture_effect)?;
+    let decal = match texture_effect.texture_type {
+        TextureType::ProjectedLight => ClusteredDecal {
+            emissive_texture: Some(image),
+            ..Default::default()
+        },
+        _ => ClusteredDecal {
+            base_color_texture: Some(image),
+            ..Default::default()
+        },
+    };
+    Some(
+        commands
+            .spawn((decal, transform, ChildOf(parent_entity)))
+            .id(),
+    )
+}
+
+/// The decal transform mapping the unit cube onto the texture effect's projection
+fn projected_texture_transform(texture_effect: &NiTextureEffect) -> Option<Transform> {
+    // The columns hold the NetImmerse rows, one per texture coordinate
+    let s_axis = texture_effect.projection_matrix.x_axis;
+    let t_axis = texture_effect.projection_matrix.y_axis;
+    // Parallel s and t axes don't span a plane, otherwise the mapping is always invertible
+    let normal = s_axis.cross(t_axis).try_normalize()?;
+    let translation = texture_effect.projection_translation;
+    let center = normal.dot(texture_effect.translation);
+    // World to decal space: s and t recentered on 0 with t pointing up, depth along the normal
+    let world_to_decal = Mat4::from_cols(
+        Vec4::new(
+            s_axis.x,
+            -t_axis.x,
+            normal.x / NIF_PROJECTED_TEXTURE_DEPTH,
+            0.0,
+        ),
+        Vec4::new(
+            s_axis.y,
+            -t_axis.y,
+            normal.y / NIF_PROJECTED_TEXTURE_DEPTH,
+            0.0,
+        ),
+        Vec4::new(
+            s_axis.z,
+            -t_axis.z,
+            normal.z / NIF_PROJECTED_TEXTURE_DEPTH,
+            0.0,
+        ),
+        Vec4::new(
+            translation.x - 0.5,
+            0.5 - translation.y,
+            -center / NIF_PROJECTED_TEXTURE_DEPTH,
+            1.0,
+        ),
+    );
+    Some(Transform::from_matrix(world_to_decal.inverse()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_math::{Mat3, Vec3};
+
+    fn assert_vec3_eq(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-3), "{a} != {b}");
+    }
+
+    #[test]
+    fn test_projected_texture_transform() {
+        // 100 units to a texture across the XY plane, centered on the origin
+        let mut texture_effect = NiTextureEffect {
+            projection_matrix: Mat3::from_cols(Vec3::X * 0.01, Vec3::Y * 0.01, Vec3::ZERO),
+            projection_translation: Vec3::new(0.5, 0.5, 0.0),
+            ..Default::default()
+        };
+        texture_effect.translation = Vec3::new(0.0, 0.0, 10.0);
+        let transform = projected_texture_transform(&texture_effect).unwrap();
+        // The decal center is where the projection hits the middle of the texture
+        assert_vec3_eq(transform.translation, Vec3::new(0.0, 0.0, 10.0));
+        // s runs along +X, t down the texture along -Y, depth along the projection normal
+        assert_vec3_eq(
+            transform.transform_point(Vec3::new(0.5, 0.0, 0.0)),
+            Vec3::new(50.0, 0.0, 10.0),
+        );
+        assert_vec3_eq(
+            transform.transform_point(Vec3::new(0.0, 0.5, 0.0)),
+            Vec3::new(0.0, -50.0, 10.0),
+        );
+        assert_vec3_eq(
+            transform.transform_point(Vec3::new(0.0, 0.0, 0.5)),
+            Vec3::new(0.0, 0.0, 10.0 + NIF_PROJECTED_TEXTURE_DEPTH * 0.5),
+        );
+
+        // Parallel s and t axes don't span a plane to project onto
+        texture_effect.projection_matrix.y_axis = texture_effect.projection_matrix.x_axis;
+        assert!(projected_texture_transform(&texture_effect).is_none());
+    }
+}
@@ -1,10 +1,12 @@
 mod common;
+mod encoding;
 mod load;
 mod reader;
 mod save;
 mod writer;
 
 pub use common::*;
+pub use encoding::*;
 pub use load::*;
 pub use reader::*;
 pub use save::*;
@@ -0,0 +1,63 @@
+// external imports
+use encoding_rs::{Encoding, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252};
+
+/// Letters the languages written in windows-1252 use beyond ASCII, like German and French
+const WESTERN_LETTERS: &str = "àâäçéèêëîïôöùûüßñáíóúÀÂÄÇÉÈÊËÎÏÔÖÙÛÜÑÁÍÓÚæœÆŒ";
+
+/// Letters the languages written in windows-1250 use beyond ASCII, like Polish and Czech
+const CENTRAL_LETTERS: &str = "ąćęłńóśźżĄĆĘŁŃÓŚŹŻáčďéěíňřšťúůýžÁČĎÉĚÍŇŘŠŤÚŮÝŽäöüÄÖÜß";
+
+/// Guess which of the windows-1252, 1251 or 1250 code pages `sample` is in, from its non-ASCII
+/// bytes. Strings in the sample are separated by null bytes.
+///
+/// Each code page scores the characters the sample decodes to: letters its languages use count
+/// for it, other letters against it, and symbols more so. Cyrillic also has to come in whole
+/// words, a lone Cyrillic letter inside a Latin word is more likely an accented Latin letter.
+/// Ties go to windows-1252, which is also the guess for plain ASCII.
+pub fn guess_encoding(sample: &[u8]) -> &'static Encoding {
+    if sample.is_ascii() {
+        return WINDOWS_1252;
+    }
+    [WINDOWS_1252, WINDOWS_1251, WINDOWS_1250]
+        .into_iter()
+        .map(|encoding| (score(encoding, sample), encoding))
+        .reduce(|best, next| if next.0 > best.0 { next } else { best })
+        .map_or(WINDOWS_1252, |(_, encoding)| encoding)
+}
+
+fn score(encoding: &'static Encoding, sample: &[u8]) -> i64 {
+    let (text, _) = encoding.decode_without_bom_handling(sample);
+    let chars: Vec<char> = text.chars().collect();
+    let mut score = 0;
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii() {
+            continue;
+        }
+        if !c.is_alphabetic() {
+            score -= 3;
+        } else if is_cyrillic(c) {
+            // neighbours decide whether it's part of a Cyrillic word
+            let neighbours =
+                [i.checked_sub(1), i.checked_add(1)].map(|j| j.and_then(|j| chars.get(j)));
+            for neighbour in neighbours.into_iter().flatten() {
+                if is_cyrillic(*neighbour) {
+                    score += 1;
+                } else if neighbour.is_ascii_alphabetic() {
+                    score -= 2;
+                }
+            }
+            score += 1;
+        } else if encoding == WINDOWS_1252 && WESTERN_LETTERS.contains(c)
+            || encoding == WINDOWS_1250 && CENTRAL_LETTERS.contains(c)
+        {
+            score += 1;
+        } else {
+            score -= 1;
+        }
+    }
+    score
+}
+
+fn is_cyrillic(c: char) -> bool {
+    ('\u{400}'..='\u{4FF}').contains(&c)
+}
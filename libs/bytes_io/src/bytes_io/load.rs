@@ -22,6 +22,7 @@ impl Load for String {
 impl Load for BString {
     fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
         let len = stream.load_as::<u32, usize>()?;
+        stream.check_string_len(len)?;
         Ok(stream.load_bytes(len)?.into())
     }
 }
@@ -36,7 +37,7 @@ impl<L: Load> Load for Box<L> {
 impl<L: Load> Load for Vec<L> {
     fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
         let len: u32 = stream.load()?;
-        (0..len).map(|_| stream.load()).collect()
+        stream.load_seq(len)
     }
 }
 
@@ -44,7 +45,7 @@ impl<L: Load> Load for Vec<L> {
 impl<L: Load> Load for Vec<L> {
     default fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
         let len: u32 = stream.load()?;
-        (0..len).map(|_| stream.load()).collect()
+        stream.load_seq(len)
     }
 }
 
@@ -116,6 +117,7 @@ macro_rules! impl_load {
                 fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
                     use bytemuck::{must_cast_slice_mut, zeroed_vec};
                     let len = stream.load_as::<u32, usize>()?;
+                    stream.check_alloc::<$T>(len)?;
                     let mut this = zeroed_vec(len);
                     stream.read_exact(must_cast_slice_mut(&mut this))?;
                     Ok(this)
@@ -1,9 +1,10 @@
 // rust std imports
 use std::borrow::Cow;
-use std::io::{self, Read};
+use std::fmt;
+use std::io::{self, BufReader, Read, Seek};
 
 // external imports
-use bytemuck::{cast_slice_mut, zeroed_vec, Pod};
+use bytemuck::{cast_slice_mut, pod_collect_to_vec, try_cast_slice, zeroed_vec, Pod};
 use encoding_rs::{Encoding, WINDOWS_1252};
 use memchr::memchr;
 use smart_default::SmartDefault;
@@ -11,18 +12,216 @@ use smart_default::SmartDefault;
 // internal imports
 use crate::Load;
 
+/// How many bytes of strings [`Reader::string_sample`] collects at most
+const MAX_STRING_SAMPLE_LEN: usize = 1 << 16;
+
+/// The error of a value an enum of the format doesn't have, see [`Reader::invalid_enum_value`]
+#[derive(Debug)]
+pub struct InvalidEnumValue(pub String);
+
+impl fmt::Display for InvalidEnumValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidEnumValue {}
+
 #[derive(Debug, SmartDefault)]
 pub struct Reader<'a> {
-    pub cursor: io::Cursor<&'a [u8]>,
+    source: Source<'a>,
     #[default(WINDOWS_1252)]
     pub encoding: &'static Encoding,
+    /// The version of the format being read, for values whose layout changed between versions
+    pub version: u32,
+    pub user_version: u32,
+    /// Skip over the data of [`Reader::load_bulk_vec`] instead of reading it, for walking a file
+    /// only to find where values end
+    pub skip_bulk_data: bool,
+    pub limits: ReaderLimits,
+    /// When set, the bytes of every string with non-ASCII characters are added to it, for
+    /// guessing the encoding with [`guess_encoding`](crate::guess_encoding)
+    pub string_sample: Option<Vec<u8>>,
+    /// How many bytes arrays and strings have allocated so far, see [`ReaderLimits::max_total_alloc`]
+    allocated: usize,
+}
+
+/// Limits on what the counts and lengths in a file can make a [`Reader`] allocate
+///
+/// They're checked before allocating, so a corrupt or malicious file fails to load instead of
+/// exhausting memory. Going over a limit is an [`io::ErrorKind::OutOfMemory`] error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, SmartDefault)]
+pub struct ReaderLimits {
+    /// The most elements a single array can have
+    #[default(1 << 26)]
+    pub max_array_len: usize,
+    /// The most bytes a single string can have
+    #[default(1 << 20)]
+    pub max_string_len: usize,
+    /// The most bytes the arrays and strings of a reader can allocate together
+    #[default(1 << 31)]
+    pub max_total_alloc: usize,
+    /// The most blocks a file can have
+    #[default(1 << 20)]
+    pub max_block_count: usize,
+}
+
+impl ReaderLimits {
+    /// No limits, for files from trusted sources
+    pub const UNLIMITED: Self = Self {
+        max_array_len: usize::MAX,
+        max_string_len: usize::MAX,
+        max_total_alloc: usize::MAX,
+        max_block_count: usize::MAX,
+    };
+}
+
+/// What a [`Reader`] reads from
+enum Source<'a> {
+    /// Bytes already in memory, which large arrays can be borrowed from
+    Bytes(io::Cursor<&'a [u8]>),
+    /// Any other reader, its bytes are only seen once they're read
+    Stream {
+        inner: Box<dyn StreamSource + 'a>,
+        /// How far past where reading started the stream is
+        position: u64,
+    },
+}
+
+impl Default for Source<'_> {
+    fn default() -> Self {
+        Self::Bytes(io::Cursor::default())
+    }
+}
+
+impl fmt::Debug for Source<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bytes(cursor) => f.debug_tuple("Bytes").field(cursor).finish(),
+            Self::Stream { position, .. } => f
+                .debug_struct("Stream")
+                .field("position", position)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// A stream that can move to another position, relative to where reading started
+trait StreamSource: Read {
+    fn seek_to(&mut self, from: u64, to: u64) -> io::Result<()>;
+}
+
+/// A stream that can seek anywhere
+struct Seekable<R> {
+    inner: R,
+    start: u64,
+}
+
+impl<R: Read> Read for Seekable<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Read + Seek> StreamSource for Seekable<R> {
+    fn seek_to(&mut self, _from: u64, to: u64) -> io::Result<()> {
+        self.inner
+            .seek(io::SeekFrom::Start(self.start.saturating_add(to)))?;
+        Ok(())
+    }
+}
+
+/// A stream that can only be read, it moves forward by reading and dropping bytes
+struct ForwardOnly<R>(R);
+
+impl<R: Read> Read for ForwardOnly<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R: Read> StreamSource for ForwardOnly<R> {
+    fn seek_to(&mut self, from: u64, to: u64) -> io::Result<()> {
+        let Some(len) = to.checked_sub(from) else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Can't seek back in a stream that can only be read",
+            ));
+        };
+        if io::copy(&mut (&mut self.0).take(len), &mut io::sink())? < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
 }
 
 impl<'a> Reader<'a> {
     pub fn new(bytes: &'a [u8]) -> Self {
         Self {
-            cursor: io::Cursor::new(bytes),
+            source: Source::Bytes(io::Cursor::new(bytes)),
+            ..Default::default()
+        }
+    }
+
+    /// Read straight from a stream, like a decompressor, without loading it into memory first.
+    /// Reading is buffered. Moving back with [`Reader::set_position`] fails, moving forward reads
+    /// over the bytes in between.
+    pub fn from_read(reader: impl Read + 'a) -> Self {
+        Self {
+            source: Source::Stream {
+                inner: Box::new(ForwardOnly(BufReader::new(reader))),
+                position: 0,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Read straight from a seekable stream, like a file or an archive entry, starting at its
+    /// current position. Reading is buffered, positions are relative to where reading started.
+    pub fn from_read_seek(mut reader: impl Read + Seek + 'a) -> io::Result<Self> {
+        let start = reader.stream_position()?;
+        Ok(Self {
+            source: Source::Stream {
+                inner: Box::new(Seekable {
+                    inner: BufReader::new(reader),
+                    start,
+                }),
+                position: 0,
+            },
             ..Default::default()
+        })
+    }
+
+    /// How many bytes into the data reading is
+    pub fn position(&self) -> u64 {
+        match &self.source {
+            Source::Bytes(cursor) => cursor.position(),
+            Source::Stream { position, .. } => *position,
+        }
+    }
+
+    /// Continue reading `position` bytes into the data
+    pub fn set_position(&mut self, position: u64) -> io::Result<()> {
+        match &mut self.source {
+            Source::Bytes(cursor) => cursor.set_position(position),
+            Source::Stream {
+                inner,
+                position: current,
+            } => {
+                if position != *current {
+                    inner.seek_to(*current, position)?;
+                    *current = position;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// All the bytes being read, when they're in memory
+    pub fn source_bytes(&self) -> Option<&'a [u8]> {
+        match &self.source {
+            Source::Bytes(cursor) => Some(*cursor.get_ref()),
+            Source::Stream { .. } => None,
         }
     }
 
@@ -33,6 +232,18 @@ impl<'a> Reader<'a> {
         Err(io::Error::new(io::ErrorKind::InvalidData, message.into()))
     }
 
+    /// Like [`Reader::error`], for a value an enum doesn't have. The error holds an
+    /// [`InvalidEnumValue`], so it can be told apart from other invalid data.
+    pub fn invalid_enum_value<M, T>(message: M) -> io::Result<T>
+    where
+        M: Into<Cow<'static, str>>,
+    {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            InvalidEnumValue(message.into().into_owned()),
+        ))
+    }
+
     pub fn load<L>(&mut self) -> io::Result<L>
     where
         L: Load,
@@ -49,9 +260,72 @@ impl<'a> Reader<'a> {
             .map_or_else(|_| Self::error("Invalid 'Load As' Conversion"), |value| Ok(value))
     }
 
+    /// Check that an array of `len` values of `T` stays inside the limits, and count it towards
+    /// the total, before allocating it. Loading checks its own arrays, this is for values that
+    /// allocate room for a count themselves.
+    pub fn check_alloc<T>(&mut self, len: usize) -> io::Result<()> {
+        self.check_array_len(len)?;
+        self.count_alloc(len.checked_mul(size_of::<T>()))
+    }
+
+    /// Count `size` bytes towards the total, `None` when the size overflowed
+    fn count_alloc(&mut self, size: Option<usize>) -> io::Result<()> {
+        match size.and_then(|size| self.allocated.checked_add(size)) {
+            Some(allocated) if allocated <= self.limits.max_total_alloc => {
+                self.allocated = allocated;
+                Ok(())
+            }
+            _ => Self::limit_error(format!(
+                "Allocations are over the limit of {} bytes",
+                self.limits.max_total_alloc
+            )),
+        }
+    }
+
+    /// Check that an array of `len` elements stays inside the limits, without counting it
+    /// towards the total, for arrays that are borrowed rather than allocated
+    pub fn check_array_len(&self, len: usize) -> io::Result<()> {
+        if len > self.limits.max_array_len {
+            return Self::limit_error(format!(
+                "Array of {len} elements is over the limit of {}",
+                self.limits.max_array_len
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that a string of `len` bytes stays inside the limits
+    pub fn check_string_len(&self, len: usize) -> io::Result<()> {
+        if len > self.limits.max_string_len {
+            return Self::limit_error(format!(
+                "String of {len} bytes is over the limit of {}",
+                self.limits.max_string_len
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that a file of `count` blocks stays inside the limits
+    pub fn check_block_count(&self, count: usize) -> io::Result<()> {
+        if count > self.limits.max_block_count {
+            return Self::limit_error(format!(
+                "{count} blocks are over the limit of {}",
+                self.limits.max_block_count
+            ));
+        }
+        Ok(())
+    }
+
+    fn limit_error<T>(message: String) -> io::Result<T> {
+        Err(io::Error::new(io::ErrorKind::OutOfMemory, message))
+    }
+
+    /// Load `len` bytes, counting them towards the total. Callers check `len` against the limit
+    /// that fits what the bytes are, like [`Reader::check_string_len`] for strings.
     pub fn load_bytes(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        self.count_alloc(Some(len))?;
         let mut bytes = vec![0; len];
-        self.cursor.read_exact(&mut bytes)?;
+        self.read_exact(&mut bytes)?;
         Ok(bytes)
     }
 
@@ -62,7 +336,10 @@ impl<'a> Reader<'a> {
     {
         len.try_into().map_or_else(
             |_| Self::error("Invalid integer length"),
-            |i| (0..i).map(|_| self.load()).collect(),
+            |i| {
+                self.check_alloc::<L>(i)?;
+                (0..i).map(|_| self.load()).collect()
+            },
         )
     }
 
@@ -73,6 +350,7 @@ impl<'a> Reader<'a> {
         len.try_into().map_or_else(
             |_| Self::error("Invalid integer length"),
             |i| {
+                self.check_alloc::<P>(i)?;
                 let mut this = zeroed_vec(i);
                 self.read_exact(cast_slice_mut(&mut this))?;
                 Ok(this)
@@ -80,6 +358,59 @@ impl<'a> Reader<'a> {
         )
     }
 
+    /// Load a large array, like vertex data, that [`Reader::skip_bulk_data`] skips over. Skipped
+    /// arrays aren't read or allocated, they load empty.
+    pub fn load_bulk_vec<P>(&mut self, len: impl TryInto<usize>) -> io::Result<Vec<P>>
+    where
+        P: Pod,
+    {
+        let slice = self.load_slice(len)?;
+        // borrowed arrays are only allocated here
+        if let Cow::Borrowed(borrowed) = &slice {
+            self.check_alloc::<P>(borrowed.len())?;
+        }
+        Ok(slice.into_owned())
+    }
+
+    /// Load a large array borrowed from the bytes being read, or copied when they aren't aligned
+    /// for `P`. Skipped like [`Reader::load_bulk_vec`].
+    pub fn load_slice<P>(&mut self, len: impl TryInto<usize>) -> io::Result<Cow<'a, [P]>>
+    where
+        P: Pod,
+    {
+        let Ok(len) = len.try_into() else {
+            return Self::error("Invalid integer length");
+        };
+        self.check_array_len(len)?;
+        let Some(size) = len.checked_mul(size_of::<P>()) else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        let Some(bytes) = self.source_bytes() else {
+            // streams have nothing to borrow from
+            if self.skip_bulk_data {
+                self.set_position(self.position().saturating_add(size as u64))?;
+                return Ok(Cow::Borrowed(&[]));
+            }
+            return Ok(Cow::Owned(self.load_vec(len)?));
+        };
+        let start = usize::try_from(self.position()).unwrap_or(usize::MAX);
+        let Some(slice) = start
+            .checked_add(size)
+            .and_then(|end| bytes.get(start..end))
+        else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        self.set_position((start + size) as u64)?;
+        if self.skip_bulk_data {
+            return Ok(Cow::Borrowed(&[]));
+        }
+        if let Ok(slice) = try_cast_slice(slice) {
+            return Ok(Cow::Borrowed(slice));
+        }
+        self.check_alloc::<P>(len)?;
+        Ok(Cow::Owned(pod_collect_to_vec(slice)))
+    }
+
     pub fn load_string<T>(&mut self, len: usize) -> io::Result<T>
     where
         for<'any> Cow<'any, str>: Into<T>,
@@ -89,10 +420,18 @@ impl<'a> Reader<'a> {
         }
 
         // truncate at first null character
+        self.check_string_len(len)?;
         let mut bytes = self.load_bytes(len)?;
         if let Some(index) = memchr(0, &bytes) {
             bytes.truncate(index);
         }
+        if let Some(sample) = &mut self.string_sample
+            && !bytes.is_ascii()
+            && sample.len() < MAX_STRING_SAMPLE_LEN
+        {
+            sample.extend_from_slice(&bytes);
+            sample.push(0);
+        }
 
         if let (bytes, _, false) = self.encoding.decode(&bytes) {
             return Ok(bytes.into());
@@ -100,7 +439,7 @@ impl<'a> Reader<'a> {
 
         Err(io::Error::new(
             io::ErrorKind::InvalidData,
-            format!("decode error: offset={}", self.cursor.position()),
+            format!("decode error: offset={}", self.position()),
         ))
     }
 
@@ -108,30 +447,41 @@ impl<'a> Reader<'a> {
     where
         L: Copy + Load + PartialEq,
     {
-        let pos = self.cursor.position();
+        let pos = self.position();
         let value: L = self.load()?;
         if value == expected {
             Ok(())
         } else {
-            self.cursor.set_position(pos);
+            self.set_position(pos)?;
             Self::error("Unexpected Value")
         }
     }
 
     pub fn skip(&mut self, len: u32) -> io::Result<u64> {
-        let old_pos = self.cursor.position();
-        let new_pos = old_pos + u64::from(len);
-        if new_pos <= (self.cursor.get_ref().len() as u64) {
-            self.cursor.set_position(new_pos);
-            Ok(new_pos)
-        } else {
-            Err(io::Error::new(io::ErrorKind::InvalidData, "Skip out of bounds"))
+        let new_pos = self.position() + u64::from(len);
+        if self
+            .source_bytes()
+            .is_some_and(|bytes| new_pos > bytes.len() as u64)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Skip out of bounds",
+            ));
         }
+        self.set_position(new_pos)?;
+        Ok(new_pos)
     }
 }
 
 impl Read for Reader<'_> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.cursor.read(buf)
+        match &mut self.source {
+            Source::Bytes(cursor) => cursor.read(buf),
+            Source::Stream { inner, position } => {
+                let len = inner.read(buf)?;
+                *position += len as u64;
+                Ok(len)
+            }
+        }
     }
 }
@@ -18,6 +18,9 @@ pub struct Writer {
     pub context: HashMap<u64, u64>,
     #[default(WINDOWS_1252)]
     pub encoding: &'static Encoding,
+    /// The version of the format being written, for values whose layout changed between versions
+    pub version: u32,
+    pub user_version: u32,
 }
 
 impl Writer {
@@ -1,8 +1,19 @@
 use proc_macro::TokenStream;
 use quote::quote;
 
-#[doc(hidden)]
-#[proc_macro_derive(LoadSave)]
+/// Derive `Load` and `Save`, field by field in declaration order. Fields take options in a
+/// `#[load_save(...)]` attribute:
+///
+/// - `count = T`: a `Vec` prefixed by its length as `T` instead of `u32`
+/// - `bool = T`: a `bool` stored as the integer `T`
+/// - `bool`: a `bool` stored by `load_bool` and `save_bool`, which must be in scope
+/// - `flag`: an `Option` preceded by a `bool` saying whether it's there, stored like `bool`
+/// - `present_if = field`: only there when the earlier `bool` field is set
+/// - `since = V` and `until = V`: only there in versions from or up to `V`, both inclusive
+///
+/// Fields that aren't there load as their value in `<Self as Default>::default()`, so a
+/// `SmartDefault` `#[default(...)]` on the field is honoured.
+#[proc_macro_derive(LoadSave, attributes(load_save))]
 pub fn derive_load_save(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
 
@@ -17,9 +28,15 @@ fn impl_load_save_for_struct(input: &syn::DeriveInput) -> TokenStream {
     let ident = &input.ident;
     let fields: Vec<_> = get_struct_fields(&input.data).collect();
     if fields.is_empty() {
-        impl_load_save_for_bitflags(ident).into()
-    } else {
-        impl_load_save_for_struct_with_named_fields(ident, &fields).into()
+        return impl_load_save_for_bitflags(ident).into();
+    }
+    match fields
+        .iter()
+        .map(|field| FieldOptions::parse(field))
+        .collect::<syn::Result<Vec<_>>>()
+    {
+        Ok(options) => impl_load_save_for_struct_with_named_fields(ident, &fields, &options).into(),
+        Err(error) => error.to_compile_error().into(),
     }
 }
 
@@ -43,16 +60,47 @@ fn impl_load_save_for_bitflags(ident: &syn::Ident) -> impl Into<TokenStream> {
     }
 }
 
-fn impl_load_save_for_struct_with_named_fields(ident: &syn::Ident, fields: &[&syn::Ident]) -> impl Into<TokenStream> {
+fn impl_load_save_for_struct_with_named_fields(
+    ident: &syn::Ident,
+    fields: &[&syn::Field],
+    options: &[FieldOptions],
+) -> impl Into<TokenStream> {
+    let field_idents: Vec<_> = fields
+        .iter()
+        .filter_map(|field| field.ident.as_ref())
+        .collect();
+    let loads = field_idents.iter().zip(options).map(|(field, options)| {
+        let load = options.load();
+        options.load_condition().map_or_else(
+            || quote! { let #field = #load; },
+            |condition| quote! { let #field = if #condition { #load } else { defaults.#field }; },
+        )
+    });
+    // built once, only for structs with fields that can be absent
+    let defaults = options
+        .iter()
+        .any(|options| options.load_condition().is_some())
+        .then(|| quote! { let defaults = <Self as Default>::default(); });
+    let saves = field_idents.iter().zip(options).map(|(field, options)| {
+        let save = options.save(field);
+        options.save_condition().map_or_else(
+            || save.clone(),
+            |condition| quote! { if #condition { #save } },
+        )
+    });
     quote! {
         const _: () = {
             use crate::prelude::*;
 
             impl Load for #ident {
                 fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
+                    #defaults
+                    #(
+                        #loads
+                    )*
                     Ok(Self {
                         #(
-                            #fields: stream.load()?,
+                            #field_idents,
                         )*
                     })
                 }
@@ -61,7 +109,7 @@ fn impl_load_save_for_struct_with_named_fields(ident: &syn::Ident, fields: &[&sy
             impl Save for #ident {
                 fn save(&self, stream: &mut Writer) -> io::Result<()> {
                     #(
-                        stream.save(&self.#fields)?;
+                        #saves
                     )*
                     Ok(())
                 }
@@ -70,6 +118,152 @@ fn impl_load_save_for_struct_with_named_fields(ident: &syn::Ident, fields: &[&sy
     }
 }
 
+/// How a `bool` is stored
+enum BoolRepr {
+    /// By `load_bool` and `save_bool`, for formats where it depends on the version
+    Versioned,
+    /// As an integer type
+    Integer(Box<syn::Type>),
+}
+
+/// The options of a field from its `#[load_save(...)]` attribute
+#[derive(Default)]
+struct FieldOptions {
+    count: Option<syn::Type>,
+    bool_repr: Option<BoolRepr>,
+    flag: bool,
+    present_if: Option<syn::Ident>,
+    since: Option<syn::Expr>,
+    until: Option<syn::Expr>,
+}
+
+impl FieldOptions {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("load_save"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("count") {
+                    options.count = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("bool") {
+                    options.bool_repr = Some(if meta.input.peek(syn::Token![=]) {
+                        BoolRepr::Integer(meta.value()?.parse()?)
+                    } else {
+                        BoolRepr::Versioned
+                    });
+                } else if meta.path.is_ident("flag") {
+                    options.flag = true;
+                } else if meta.path.is_ident("present_if") {
+                    options.present_if = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("since") {
+                    options.since = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("until") {
+                    options.until = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("unknown load_save option"));
+                }
+                Ok(())
+            })?;
+        }
+        if options.count.is_some() && (options.bool_repr.is_some() || options.flag) {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`count` can't be combined with `bool` or `flag`",
+            ));
+        }
+        Ok(options)
+    }
+
+    /// Load a bool the way the `bool` option says
+    fn load_bool(&self) -> proc_macro2::TokenStream {
+        if let Some(BoolRepr::Integer(repr)) = &self.bool_repr {
+            quote! { (stream.load::<#repr>()? != 0) }
+        } else {
+            quote! { stream.load_bool()? }
+        }
+    }
+
+    fn save_bool(&self, value: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        if let Some(BoolRepr::Integer(repr)) = &self.bool_repr {
+            quote! { stream.save_as::<#repr>(#value)?; }
+        } else {
+            quote! { stream.save_bool(#value)?; }
+        }
+    }
+
+    fn load(&self) -> proc_macro2::TokenStream {
+        if let Some(count) = &self.count {
+            return quote! {{
+                let len: #count = stream.load()?;
+                stream.load_seq(len)?
+            }};
+        }
+        if self.flag {
+            let flag = self.load_bool();
+            return quote! {
+                if #flag { Some(stream.load()?) } else { None }
+            };
+        }
+        if self.bool_repr.is_some() {
+            return self.load_bool();
+        }
+        quote! { stream.load()? }
+    }
+
+    fn save(&self, field: &syn::Ident) -> proc_macro2::TokenStream {
+        if let Some(count) = &self.count {
+            return quote! {
+                stream.save_as::<#count>(self.#field.len())?;
+                stream.save_seq(&self.#field)?;
+            };
+        }
+        if self.flag {
+            let flag = self.save_bool(&quote! { self.#field.is_some() });
+            return quote! {
+                #flag
+                if let Some(value) = &self.#field {
+                    stream.save(value)?;
+                }
+            };
+        }
+        if self.bool_repr.is_some() {
+            return self.save_bool(&quote! { self.#field });
+        }
+        quote! { stream.save(&self.#field)?; }
+    }
+
+    /// Whether the field is there when loading, `None` when it always is
+    fn load_condition(&self) -> Option<proc_macro2::TokenStream> {
+        let present_if = self.present_if.as_ref().map(|flag| quote! { #flag });
+        self.condition(present_if)
+    }
+
+    /// Whether the field is there when saving, `None` when it always is
+    fn save_condition(&self) -> Option<proc_macro2::TokenStream> {
+        let present_if = self.present_if.as_ref().map(|flag| quote! { self.#flag });
+        self.condition(present_if)
+    }
+
+    fn condition(
+        &self,
+        present_if: Option<proc_macro2::TokenStream>,
+    ) -> Option<proc_macro2::TokenStream> {
+        let since = self
+            .since
+            .as_ref()
+            .map(|since| quote! { stream.version >= #since });
+        let until = self
+            .until
+            .as_ref()
+            .map(|until| quote! { stream.version <= #until });
+        let conditions: Vec<_> = [since, until, present_if].into_iter().flatten().collect();
+        (!conditions.is_empty()).then(|| quote! { #(#conditions)&&* })
+    }
+}
+
 fn impl_load_save_for_enum(input: &syn::DeriveInput) -> TokenStream {
     let variants = match &input.data {
         syn::Data::Enum(e) => &e.variants,
@@ -80,8 +274,14 @@ fn impl_load_save_for_enum(input: &syn::DeriveInput) -> TokenStream {
     let self_ident = &input.ident;
 
     let variant_idents: Vec<_> = variants.iter().map(|v| &v.ident).collect();
-    let variant_values: Vec<_> = variants.iter().map(|v| &v.discriminant.as_ref().unwrap().1).collect();
-    let variant_strings: Vec<_> = variant_idents.iter().map(|id| get_literal_str(id)).collect();
+    let variant_values: Vec<_> = variants
+        .iter()
+        .map(|v| &v.discriminant.as_ref().unwrap().1)
+        .collect();
+    let variant_strings: Vec<_> = variant_idents
+        .iter()
+        .map(|id| get_literal_str(id))
+        .collect();
 
     let output = quote! {
         const _: () = {
@@ -106,7 +306,9 @@ fn impl_load_save_for_enum(input: &syn::DeriveInput) -> TokenStream {
             impl Load for #self_ident {
                 fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
                     let value: #repr_ident = stream.load()?;
-                    Ok(value.try_into().unwrap_or_default())
+                    value.try_into().or_else(|()| {
+                        Reader::invalid_enum_value(format!("Invalid {}: {value}", stringify!(#self_ident)))
+                    })
                 }
             }
 
@@ -135,12 +337,12 @@ fn get_literal_str(id: &syn::Ident) -> syn::LitStr {
     syn::LitStr::new(&id.to_string(), id.span())
 }
 
-fn get_struct_fields(data: &syn::Data) -> impl Iterator<Item = &syn::Ident> {
+fn get_struct_fields(data: &syn::Data) -> impl Iterator<Item = &syn::Field> {
     let fields = match data {
         syn::Data::Struct(syn::DataStruct {
             fields: syn::Fields::Named(f),
             ..
-        }) => Some(f.named.iter().filter_map(|f| f.ident.as_ref())),
+        }) => Some(f.named.iter().filter(|f| f.ident.is_some())),
         _ => None,
     };
     fields.into_iter().flatten()
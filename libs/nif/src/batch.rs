@@ -0,0 +1,101 @@
+//! Loading many NIFs at once, like a whole Data Files directory, spread over every core
+
+// rust std imports
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// external imports
+use rayon::prelude::*;
+
+// internal imports
+use crate::prelude::*;
+
+/// How loading one file of a batch went
+#[derive(Debug)]
+pub struct NifBatchResult<S> {
+    /// The path of the file, or the index of the buffer in the batch
+    pub source: S,
+    pub result: Result<NiStream, NifError>,
+    /// How long reading and parsing the file took
+    pub elapsed: Duration,
+}
+
+impl<S> NifBatchResult<S> {
+    fn timed(source: S, load: impl FnOnce() -> Result<NiStream, NifError>) -> Self {
+        let start = Instant::now();
+        let result = load();
+        Self {
+            source,
+            result,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Read and parse every file on the rayon thread pool. Results are in the order of `paths`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use nif::batch::load_paths;
+///
+/// let paths: Vec<_> = std::fs::read_dir("Data Files/Meshes")?
+///     .filter_map(|entry| Some(entry.ok()?.path()))
+///     .collect();
+/// for file in load_paths(&paths) {
+///     if let Err(error) = &file.result {
+///         println!("{}: {error} ({:?})", file.source.display(), file.elapsed);
+///     }
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn load_paths<P>(paths: &[P]) -> Vec<NifBatchResult<PathBuf>>
+where
+    P: AsRef<Path> + Sync,
+{
+    paths
+        .par_iter()
+        .map(|path| {
+            let path = path.as_ref();
+            NifBatchResult::timed(path.to_path_buf(), || NiStream::from_path(path))
+        })
+        .collect()
+}
+
+/// Parse every buffer on the rayon thread pool. Results are in the order of `buffers`, the
+/// source of each is the index of its buffer.
+pub fn load_buffers<B>(buffers: &[B]) -> Vec<NifBatchResult<usize>>
+where
+    B: AsRef<[u8]> + Sync,
+{
+    buffers
+        .par_iter()
+        .enumerate()
+        .map(|(index, bytes)| NifBatchResult::timed(index, || NiStream::from_bytes(bytes.as_ref())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_buffers_keeps_order() {
+        let mut stream = NiStream::new();
+        let root = stream.insert(NiNode::default());
+        stream.roots.push(root.cast());
+        let bytes = stream.save_bytes().unwrap();
+
+        let results = load_buffers(&[bytes.clone(), b"not a nif\n".to_vec(), bytes]);
+        assert_eq!(
+            results.iter().map(|file| file.source).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert!(results[0].is_ok() && results[2].is_ok());
+        assert!(matches!(results[1].result, Err(NifError::BadHeader)));
+    }
+}
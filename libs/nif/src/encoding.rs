@@ -0,0 +1,96 @@
+// external imports
+use encoding_rs::{Encoding, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252};
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::prelude::*;
+
+/// The code page the strings of a NIF, like node names and texture paths, are stored in
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NifEncoding {
+    /// The English, German and French releases
+    #[default]
+    Windows1252,
+    /// The Russian release
+    Windows1251,
+    /// The Polish and Czech releases
+    Windows1250,
+    /// Guess from the strings of the file, see `guess_encoding`. Loading replaces it with the code
+    /// page it guessed, so saving writes the strings back the same way.
+    Detect,
+}
+
+impl NifEncoding {
+    /// The code page strings are read and written with, `Detect` falls back to windows-1252
+    pub fn encoding(self) -> &'static Encoding {
+        match self {
+            Self::Windows1252 | Self::Detect => WINDOWS_1252,
+            Self::Windows1251 => WINDOWS_1251,
+            Self::Windows1250 => WINDOWS_1250,
+        }
+    }
+
+    /// Guess the code page of a NIF from the strings of its blocks. Vertex and pixel data is
+    /// skipped over, and blocks that fail to load or go over `limits` end the guess early.
+    pub fn detect(bytes: &[u8], limits: ReaderLimits) -> Self {
+        let mut stream = Reader::new(bytes);
+        stream.limits = limits;
+        stream.skip_bulk_data = true;
+        stream.string_sample = Some(Vec::new());
+        if let Ok(header) = load_header(&mut stream) {
+            let num_objects = header.num_blocks as usize;
+            for block_index in 0..num_objects {
+                if load_block(&mut stream, block_index, num_objects).is_err() {
+                    break;
+                }
+            }
+        }
+        let guess = guess_encoding(&stream.string_sample.unwrap_or_default());
+        if guess == WINDOWS_1251 {
+            Self::Windows1251
+        } else if guess == WINDOWS_1250 {
+            Self::Windows1250
+        } else {
+            Self::Windows1252
+        }
+    }
+
+    /// The code page to load `bytes` with, guessing it if this is `Detect`
+    pub(crate) fn resolve(&mut self, bytes: &[u8], limits: ReaderLimits) -> &'static Encoding {
+        if *self == Self::Detect {
+            *self = Self::detect(bytes, limits);
+        }
+        self.encoding()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_round_trip() {
+        let mut stream = NiStream::new();
+        stream.encoding = NifEncoding::Windows1251;
+        let root = stream.insert(NiNode::default());
+        stream.get_mut(root).unwrap().name = "Стеклянный кинжал".to_string();
+        stream.roots.push(root.cast());
+        let bytes = stream.save_bytes().unwrap();
+
+        let mut loaded = NiStream::new();
+        loaded.load_bytes(&bytes).unwrap();
+        assert_ne!(
+            loaded.objects_of_type::<NiNode>().next().unwrap().name,
+            "Стеклянный кинжал"
+        );
+
+        let mut loaded = NiStream::new();
+        loaded.encoding = NifEncoding::Detect;
+        loaded.load_bytes(&bytes).unwrap();
+        assert_eq!(loaded.encoding, NifEncoding::Windows1251);
+        assert_eq!(
+            loaded.objects_of_type::<NiNode>().next().unwrap().name,
+            "Стеклянный кинжал"
+        );
+    }
+}
@@ -0,0 +1,160 @@
+// rust std imports
+use std::fmt;
+
+// internal imports
+use crate::prelude::*;
+
+/// Everything that can go wrong loading a NIF. Errors inside a block say which block it was,
+/// its type name and the byte offset it starts at.
+#[derive(Debug)]
+pub enum NifError {
+    /// Reading the file failed, or it ended outside of any block
+    Io(io::Error),
+    /// The file doesn't start with a NetImmerse or Gamebryo header
+    BadHeader,
+    /// The blocks of the file are of a version that isn't supported
+    UnsupportedVersion(u32),
+    /// A block has a type name no block type goes by
+    UnknownBlockType {
+        block_index: usize,
+        type_name: String,
+        offset: u64,
+    },
+    /// The file ends in the middle of a block
+    TruncatedBlock {
+        block_index: usize,
+        type_name: String,
+        offset: u64,
+    },
+    /// A block holds a value its type doesn't allow, like an unknown key type
+    InvalidEnumValue {
+        block_index: usize,
+        type_name: String,
+        offset: u64,
+        message: String,
+    },
+    /// A block fails to load for another reason, like counts that don't add up
+    InvalidBlock {
+        block_index: usize,
+        type_name: String,
+        offset: u64,
+        message: String,
+    },
+    /// A block asks for more memory than the limits of the stream allow, see `ReaderLimits`.
+    /// `block_index` is `None` for the header and the root list.
+    LimitExceeded {
+        block_index: Option<usize>,
+        type_name: String,
+        offset: u64,
+        message: String,
+    },
+    /// A block links to a block index past the end of the file
+    InvalidLink {
+        block_index: usize,
+        type_name: String,
+        offset: u64,
+        link: u32,
+    },
+}
+
+impl fmt::Display for NifError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::BadHeader => write!(f, "Invalid NIF Header"),
+            Self::UnsupportedVersion(version) => write!(f, "Unsupported NIF Version {version:#x}"),
+            Self::UnknownBlockType {
+                block_index,
+                type_name,
+                offset,
+            } => write!(
+                f,
+                "Block {block_index} at offset {offset} has unknown type {type_name}"
+            ),
+            Self::TruncatedBlock {
+                block_index,
+                type_name,
+                offset,
+            } => write!(
+                f,
+                "Block {block_index} ({type_name}) at offset {offset} is cut short"
+            ),
+            Self::InvalidEnumValue {
+                block_index,
+                type_name,
+                offset,
+                message,
+            }
+            | Self::InvalidBlock {
+                block_index,
+                type_name,
+                offset,
+                message,
+            } => write!(
+                f,
+                "Block {block_index} ({type_name}) at offset {offset}: {message}"
+            ),
+            Self::LimitExceeded {
+                block_index: Some(block_index),
+                type_name,
+                offset,
+                message,
+            } => write!(
+                f,
+                "Block {block_index} ({type_name}) at offset {offset} is over a limit: {message}"
+            ),
+            Self::LimitExceeded {
+                block_index: None,
+                type_name,
+                offset,
+                message,
+            } => write!(
+                f,
+                "The {type_name} at offset {offset} is over a limit: {message}"
+            ),
+            Self::InvalidLink {
+                block_index,
+                type_name,
+                offset,
+                link,
+            } => write!(
+                f,
+                "Block {block_index} ({type_name}) at offset {offset} links to missing block {link}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NifError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for NifError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<NifError> for io::Error {
+    fn from(error: NifError) -> Self {
+        match error {
+            NifError::Io(error) => error,
+            error => Self::new(io::ErrorKind::InvalidData, error),
+        }
+    }
+}
+
+/// A problem lenient loading recovered from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NifDiagnostic {
+    /// The block the problem is in, `None` for the root list
+    pub block_index: Option<usize>,
+    pub message: String,
+    /// How many bytes were skipped to pick loading up again
+    pub skipped_bytes: u64,
+}
@@ -1,7 +1,16 @@
+#[cfg(feature = "batch")]
+pub mod batch;
+pub mod encoding;
+pub mod error;
 pub mod loader;
+pub mod pixel_data;
 pub mod types;
+pub use bytes_io::ReaderLimits;
+pub use encoding::NifEncoding;
+pub use error::{NifDiagnostic, NifError};
 pub use types::*;
 
+pub(crate) mod load;
 pub(crate) mod macros;
 
 #[allow(unused_imports)]
@@ -10,6 +19,7 @@ pub(crate) mod prelude {
 
     // internal imports
     pub use bytes_io::*;
+    pub use load::*;
     pub use macros::*;
     pub use nif_macros::*;
 
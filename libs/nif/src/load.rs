@@ -0,0 +1,344 @@
+// external imports
+use slotmap::Key;
+
+// internal imports
+use crate::prelude::*;
+
+/// Load the header every NIF starts with and check its blocks are of a supported version. The
+/// version is kept on the stream for blocks whose layout depends on it.
+pub fn load_header(stream: &mut Reader<'_>) -> Result<NiHeader, NifError> {
+    let header: NiHeader = match stream.load() {
+        Ok(header) => header,
+        Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+            return Err(NifError::BadHeader);
+        }
+        Err(error) => return Err(limit_error(error, "header", 0)),
+    };
+    if !NiHeader::is_supported_version(header.version) {
+        return Err(NifError::UnsupportedVersion(header.version));
+    }
+    stream.version = header.version;
+    stream.user_version = header.user_version;
+    Ok(header)
+}
+
+/// Load block `block_index` of `num_objects`, checking that its links stay inside the file
+pub fn load_block(
+    stream: &mut Reader<'_>,
+    block_index: usize,
+    num_objects: usize,
+) -> Result<NiType, NifError> {
+    let offset = stream.position();
+    let (ni_type, type_name) = load_block_unchecked(stream, block_index)?;
+    check_links(&ni_type, block_index, &type_name, offset, num_objects)?;
+    Ok(ni_type)
+}
+
+/// Load block `block_index` like [`load_block`], but recover from a block that fails to load: it
+/// becomes an `NiType::Empty` placeholder so later link indices stay valid, and loading picks up
+/// again at the next length prefixed type name. Blocks linking past the end of the file are kept.
+pub fn load_block_lenient(
+    stream: &mut Reader<'_>,
+    block_index: usize,
+    num_objects: usize,
+    diagnostics: &mut Vec<NifDiagnostic>,
+) -> NiType {
+    let offset = stream.position();
+    let error = match load_block_unchecked(stream, block_index) {
+        Ok((ni_type, type_name)) => {
+            if let Err(error) = check_links(&ni_type, block_index, &type_name, offset, num_objects)
+            {
+                diagnostics.push(NifDiagnostic {
+                    block_index: Some(block_index),
+                    message: error.to_string(),
+                    skipped_bytes: 0,
+                });
+            }
+            return ni_type;
+        }
+        Err(error) => error,
+    };
+    // resyncing needs the bytes in memory, a stream can only go on from where the block failed
+    let Some(bytes) = stream.source_bytes() else {
+        diagnostics.push(NifDiagnostic {
+            block_index: Some(block_index),
+            message: error.to_string(),
+            skipped_bytes: 0,
+        });
+        return NiType::Empty;
+    };
+    let resume_at = find_next_block(bytes, offset + 1).unwrap_or(bytes.len() as u64);
+    // moving around in bytes in memory can't fail
+    stream.set_position(resume_at).ok();
+    diagnostics.push(NifDiagnostic {
+        block_index: Some(block_index),
+        message: error.to_string(),
+        skipped_bytes: resume_at.saturating_sub(offset),
+    });
+    NiType::Empty
+}
+
+/// Load the root links after the blocks. If they can't be read where the blocks end, they're
+/// guessed from the end of the file, where the root list always is.
+pub fn load_roots_lenient(
+    stream: &mut Reader<'_>,
+    num_objects: usize,
+    diagnostics: &mut Vec<NifDiagnostic>,
+) -> Vec<NiLink<NiObject>> {
+    let is_valid = |roots: &[NiLink<NiObject>]| {
+        roots
+            .iter()
+            .all(|root| root.is_null() || link_index(root.key) < num_objects as u64)
+    };
+    if let Ok(roots) = stream.load::<Vec<NiLink<NiObject>>>()
+        && is_valid(&roots)
+    {
+        return roots;
+    }
+
+    let bytes = stream.source_bytes().unwrap_or_default();
+    for count in 1..=num_objects.min(64) {
+        let Some(start) = bytes.len().checked_sub(4 + 4 * count) else {
+            break;
+        };
+        let mut reader = Reader::new(&bytes[start..]);
+        if let Ok(roots) = reader.load::<Vec<NiLink<NiObject>>>()
+            && roots.len() == count
+            && is_valid(&roots)
+        {
+            diagnostics.push(NifDiagnostic {
+                block_index: None,
+                message: "Root list guessed from the end of the file".to_string(),
+                skipped_bytes: 0,
+            });
+            return roots;
+        }
+    }
+    diagnostics.push(NifDiagnostic {
+        block_index: None,
+        message: "Root list is missing".to_string(),
+        skipped_bytes: 0,
+    });
+    Vec::new()
+}
+
+/// Load a block and its type name
+fn load_block_unchecked(
+    stream: &mut Reader<'_>,
+    block_index: usize,
+) -> Result<(NiType, String), NifError> {
+    let offset = stream.position();
+    let type_name_bytes: BString = stream
+        .load()
+        .map_err(|error| block_error(&error, block_index, String::new(), offset))?;
+    let type_name = type_name_bytes.to_str_lossy().into_owned();
+    if !NiType::is_known_type_name(&type_name_bytes) {
+        return Err(NifError::UnknownBlockType {
+            block_index,
+            type_name,
+            offset,
+        });
+    }
+
+    // the type name isn't read again, so streams that can't seek back load too
+    match NiType::load_named(&type_name_bytes, stream) {
+        Ok(ni_type) => Ok((ni_type, type_name)),
+        Err(error) => Err(block_error(&error, block_index, type_name, offset)),
+    }
+}
+
+/// The error for a block whose body failed to load
+pub fn block_error(
+    error: &io::Error,
+    block_index: usize,
+    type_name: String,
+    offset: u64,
+) -> NifError {
+    match error.kind() {
+        io::ErrorKind::UnexpectedEof => NifError::TruncatedBlock {
+            block_index,
+            type_name,
+            offset,
+        },
+        io::ErrorKind::OutOfMemory => NifError::LimitExceeded {
+            block_index: Some(block_index),
+            type_name,
+            offset,
+            message: error.to_string(),
+        },
+        io::ErrorKind::InvalidData
+            if error
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<InvalidEnumValue>())
+                .is_some() =>
+        {
+            NifError::InvalidEnumValue {
+                block_index,
+                type_name,
+                offset,
+                message: error.to_string(),
+            }
+        }
+        _ => NifError::InvalidBlock {
+            block_index,
+            type_name,
+            offset,
+            message: error.to_string(),
+        },
+    }
+}
+
+/// The error for the header or the root list failing to load, `what` they are
+pub fn limit_error(error: io::Error, what: &str, offset: u64) -> NifError {
+    if error.kind() == io::ErrorKind::OutOfMemory {
+        NifError::LimitExceeded {
+            block_index: None,
+            type_name: what.to_string(),
+            offset,
+            message: error.to_string(),
+        }
+    } else {
+        error.into()
+    }
+}
+
+fn check_links(
+    ni_type: &NiType,
+    block_index: usize,
+    type_name: &str,
+    offset: u64,
+    num_objects: usize,
+) -> Result<(), NifError> {
+    let mut invalid_link = None;
+    ni_type.visitor(&mut |key| {
+        let link = link_index(key);
+        if !key.is_null() && link >= num_objects as u64 {
+            invalid_link.get_or_insert_with(|| u32::try_from(link).unwrap_or(u32::MAX));
+        }
+    });
+    invalid_link.map_or(Ok(()), |link| {
+        Err(NifError::InvalidLink {
+            block_index,
+            type_name: type_name.to_string(),
+            offset,
+            link,
+        })
+    })
+}
+
+/// The block index a loaded link points to, links are loaded as keys of the block index plus
+/// one, see `NiLink::load`
+pub fn link_index(key: NiKey) -> u64 {
+    (key.data().as_ffi() & u64::from(u32::MAX)).wrapping_sub(1)
+}
+
+/// The offset of the next length prefixed type name of a known block type
+fn find_next_block(bytes: &[u8], from: u64) -> Option<u64> {
+    let from = usize::try_from(from).ok()?;
+    (from..bytes.len())
+        .find(|&start| {
+            let Some(len) = bytes.get(start..start + 4) else {
+                return false;
+            };
+            let len = u32::from_le_bytes(len.try_into().unwrap_or_default()) as usize;
+            // type names are short, which rules out most positions without comparing names
+            (1..=64).contains(&len)
+                && bytes
+                    .get(start + 4..start + 4 + len)
+                    .is_some_and(NiType::is_known_type_name)
+        })
+        .map(|start| start as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_errors() {
+        let mut stream = NiStream::new();
+        let root = stream.insert(NiNode::default());
+        stream.roots.push(root.cast());
+        let bytes = stream.save_bytes().unwrap();
+
+        let truncated = NiStream::from_bytes(&bytes[..bytes.len() - 12]);
+        assert!(matches!(
+            truncated,
+            Err(NifError::TruncatedBlock { block_index: 0, .. })
+        ));
+
+        let mut renamed = bytes;
+        let name_start = renamed.windows(6).position(|w| w == b"NiNode").unwrap();
+        renamed[name_start + 5] = b'X';
+        assert!(matches!(
+            NiStream::from_bytes(&renamed),
+            Err(NifError::UnknownBlockType { block_index: 0, .. })
+        ));
+
+        // only errors made by `Reader::invalid_enum_value` are invalid enum values
+        let enum_error = Reader::invalid_enum_value::<_, ()>("Invalid KeyType").unwrap_err();
+        let other_error = Reader::error::<_, ()>("Invalid integer length").unwrap_err();
+        assert!(matches!(
+            block_error(&enum_error, 2, "NiPosData".to_string(), 64),
+            NifError::InvalidEnumValue {
+                block_index: 2,
+                offset: 64,
+                ..
+            }
+        ));
+        assert!(matches!(
+            block_error(&other_error, 2, "NiPosData".to_string(), 64),
+            NifError::InvalidBlock {
+                block_index: 2,
+                offset: 64,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_lenient_load_skips_broken_block() {
+        let mut stream = NiStream::new();
+        let children: [NiLink<NiAVObject>; 2] = ["a", "b"].map(|name| {
+            let child = stream.insert(NiNode::default());
+            stream.get_mut(child).unwrap().name = name.to_string();
+            child.cast()
+        });
+        let root = stream.insert(NiNode {
+            children: children.into(),
+            ..default()
+        });
+        stream.roots.push(root.cast());
+        let mut bytes = stream.save_bytes().unwrap();
+
+        // rename the second block to a type that doesn't exist
+        let second = bytes
+            .windows(6)
+            .enumerate()
+            .filter(|(_, window)| *window == b"NiNode")
+            .nth(1)
+            .map(|(index, _)| index)
+            .unwrap();
+        bytes[second + 5] = b'X';
+        assert!(NiStream::from_bytes(&bytes).is_err());
+
+        let (mut stream, diagnostics) = NiStream::from_bytes_lenient(&bytes).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].block_index, Some(1));
+        assert_eq!(stream.objects.len(), 3);
+        assert_eq!(stream.objects_of_type::<NiNode>().count(), 2);
+        assert_eq!(stream.roots.len(), 1);
+
+        // links to the lost block are saved as null, links to missing blocks fail to save
+        let saved = NiStream::from_bytes(&stream.save_bytes().unwrap()).unwrap();
+        let root = saved.get(saved.roots[0].cast::<NiNode>()).unwrap();
+        assert_eq!(
+            root.children.iter().filter(|child| child.is_null()).count(),
+            1
+        );
+        stream.objects.retain(
+            |_, object| !matches!(object, NiType::NiNode(node) if node.children.is_empty()),
+        );
+        assert!(stream.save_bytes().is_err());
+    }
+}
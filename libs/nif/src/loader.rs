@@ -1,13 +1,16 @@
 // rust std imports
+use std::borrow::Cow;
 use std::io::{Read, Seek};
 use std::path::Path;
 
 // external imports
 use bevy_asset::{Asset, Handle, LoadContext, RenderAssetUsages};
+use bevy_image::Image;
 use bevy_log::{error, info, warn};
 use bevy_mesh::{Indices, Mesh, PrimitiveTopology};
 use bevy_reflect::TypePath;
-use slotmap::{DenseSlotMap, Key};
+use serde::{Deserialize, Serialize};
+use slotmap::Key;
 
 // internal imports
 use crate::prelude::*;
@@ -16,142 +19,204 @@ use crate::prelude::*;
 #[derive(Clone, Debug)]
 pub enum ConsumedNiType {
     NiTriShapeData(Handle<Mesh>),
+    /// An embedded texture, decoded by the bevy side of the loader
+    NiPixelData(Handle<Image>),
 }
+/// How the shapes of a NIF are shaded once spawned
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NifShading {
+    /// Approximate the NIF material with Bevy's physically based `StandardMaterial`
+    #[default]
+    Pbr,
+    /// Reproduce the Morrowind era fixed function pipeline
+    FixedFunction,
+}
+
+/// What shapes stored without normals get instead
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NifNormalGeneration {
+    /// Split every triangle off and give it its face normal
+    #[default]
+    Flat,
+    /// Average the face normals around every shared vertex
+    Smooth,
+    /// Leave the mesh without normals
+    None,
+}
+
+/// Which NIFs are turned from the file's Z-up coordinates to Bevy's Y-up ones when spawned
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NifCoordinateConversion {
+    /// Only the main skeleton, `base_anim.nif`, everything else attaches to it or is placed in
+    /// the game's own Z-up world
+    #[default]
+    MainSkeleton,
+    /// Every NIF
+    All,
+    /// None, keep Z-up
+    None,
+}
+
+/// Settings for loading a NIF, passed with `AssetServer::load_with_settings` or a `.meta` file
+#[derive(Serialize, Deserialize, Clone, Debug, SmartDefault)]
+#[serde(default)]
+pub struct NifLoaderSettings {
+    pub shading: NifShading,
+    /// The asset folder external texture paths are relative to
+    #[default("Textures".to_string())]
+    pub texture_root: String,
+    /// Nodes and shapes with one of these names are spawned hidden
+    #[default(vec!["Tri Shadow".to_string(), "Tri QuadPatch01".to_string()])]
+    pub hidden_node_names: Vec<String>,
+    pub normal_generation: NifNormalGeneration,
+    pub coordinate_conversion: NifCoordinateConversion,
+    /// Keep the mesh data on the CPU after it's uploaded. Attached parts that mirror their
+    /// normals read it back. Skinned shapes and shapes that ignore their vertex colors keep it
+    /// either way, spawning edits their meshes.
+    #[default(true)]
+    pub keep_cpu_mesh_data: bool,
+    /// Keep loading past blocks that fail to load, see `Nif::diagnostics`
+    pub lenient: bool,
+    /// The code page of node names and texture paths, the Russian, Polish and Czech releases
+    /// don't use the default
+    pub encoding: NifEncoding,
+}
+
+impl NifLoaderSettings {
+    /// Where the loaded meshes live
+    pub fn mesh_asset_usage(&self) -> RenderAssetUsages {
+        if self.keep_cpu_mesh_data {
+            RenderAssetUsages::default()
+        } else {
+            RenderAssetUsages::RENDER_WORLD
+        }
+    }
+
+    /// Returns true if nodes and shapes named `name` are spawned hidden
+    pub fn is_hidden_node(&self, name: &str) -> bool {
+        self.hidden_node_names.iter().any(|hidden| hidden == name)
+    }
+}
+
+/// A loaded NIF. Every block stays in the stream it derefs to, so it can be inspected, modified
+/// and saved again, the Bevy assets built from blocks are kept beside it.
 #[derive(Asset, TypePath, Clone, Debug, Default)]
 pub struct Nif {
-    pub objects: DenseSlotMap<NiKey, NiType>,
-    pub roots: Vec<NiLink<NiObject>>,
+    pub stream: NiStream,
+    /// The Bevy assets built from blocks, keyed by the block they were built from
     pub block_assets: HashMap<NiKey, ConsumedNiType>,
-    pub all_keyframe_data: HashMap<NiKey, NiKeyframeData>,
-    pub all_controller_links: Vec<(NiKey, NiKeyframeController)>,
+    /// The animation text keys of the Bip01 or "Root Bone" node
     pub text_keys: Vec<NiTextKey>,
     pub node_names: HashMap<NiKey, String>,
+    /// The settings the NIF was loaded with
+    pub settings: NifLoaderSettings,
+    /// The blocks lenient loading lost, they're left as `NiType::Empty`
+    pub diagnostics: Vec<NifDiagnostic>,
+}
+
+impl std::ops::Deref for Nif {
+    type Target = NiStream;
+
+    fn deref(&self) -> &Self::Target {
+        &self.stream
+    }
 }
 
-pub const HEADER: [u8; 40] = *b"NetImmerse File Format, Version 4.0.0.2\n";
-pub const VERSION: u32 = 0x4000002;
+impl std::ops::DerefMut for Nif {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.stream
+    }
+}
+
+pub const HEADER: [u8; 40] = NiStream::HEADER;
+pub const VERSION: u32 = NiStream::VERSION;
 
 pub fn load_nif_from_path(
     path: impl AsRef<Path>,
+    settings: &NifLoaderSettings,
     load_context: &mut LoadContext<'_>,
-) -> io::Result<Nif> {
-    load_path(path, load_context)
+) -> Result<Nif, NifError> {
+    load_path(path, settings, load_context)
 }
 
 pub fn from_path_offset(
     path: impl AsRef<Path>,
     offset: u64,
     size: usize,
+    settings: &NifLoaderSettings,
     load_context: &mut LoadContext<'_>,
-) -> io::Result<Nif> {
+) -> Result<Nif, NifError> {
     let mut file = std::fs::File::open(path)?;
     file.seek(io::SeekFrom::Start(offset))?;
 
     let mut bytes = vec![0; size];
     file.read_exact(&mut bytes)?;
 
-    load_nif_bytes(&bytes, load_context)
+    load_nif_bytes(&bytes, settings, load_context)
 }
 
-pub fn load_path(path: impl AsRef<Path>, load_context: &mut LoadContext<'_>) -> io::Result<Nif> {
-    load_nif_bytes(&std::fs::read(path)?, load_context)
+pub fn load_path(
+    path: impl AsRef<Path>,
+    settings: &NifLoaderSettings,
+    load_context: &mut LoadContext<'_>,
+) -> Result<Nif, NifError> {
+    load_nif_bytes(&std::fs::read(path)?, settings, load_context)
 }
 
-pub fn from_bytes(bytes: &[u8], load_context: &mut LoadContext<'_>) -> io::Result<Nif> {
-    load_nif_bytes(bytes, load_context)
+pub fn from_bytes(
+    bytes: &[u8],
+    settings: &NifLoaderSettings,
+    load_context: &mut LoadContext<'_>,
+) -> Result<Nif, NifError> {
+    load_nif_bytes(bytes, settings, load_context)
 }
 
-/// The intended design of this function is:
-/// Read the nif file, setting up Bevy assets for any NiType that can be made into a bevy asset
-/// and store it in the denseslotmap as NiType::Empty. All other structural NiTypes are stored
-/// as-is. Any time the bevy system that actually uses the Nif asset (spawning system) comes across NiType::Empty,
-/// it should check the block_data hashmap with the corresponding key, and check which type was consumed
-/// and get the bevy asset handles through that. This results in less data duplication from the
-/// asset being loaded AND that data being in components
-pub fn load_nif_bytes(bytes: &[u8], load_context: &mut LoadContext<'_>) -> io::Result<Nif> {
-    let mut stream = Reader::new(bytes);
-    // validate header
-    let header: [u8; 40] = stream.load()?;
-    if header != HEADER {
-        return Reader::error("Invalid NIF Header");
-    }
-
-    // validate version
-    let version: u32 = stream.load()?;
-    if version != VERSION {
-        return Reader::error("Invalid NIF Version");
-    }
+/// Load a NIF into an `NiStream` and build Bevy assets for the blocks that become one.
+///
+/// Meshes are built for every `NiTriShapeData`, from arrays borrowed from `bytes`. Any time the
+/// spawning system comes across one of those blocks, it gets the asset handle from
+/// `Nif::block_assets` with the block's key.
+pub fn load_nif_bytes(
+    bytes: &[u8],
+    settings: &NifLoaderSettings,
+    load_context: &mut LoadContext<'_>,
+) -> Result<Nif, NifError> {
+    let mut stream = NiStream::new();
+    stream.encoding = settings.encoding;
+    let (mut shape_data, diagnostics) = if settings.lenient {
+        (HashMap::new(), stream.load_bytes_lenient(bytes)?)
+    } else {
+        let shape_data = stream.load_bytes_with_shape_data(bytes)?;
+        (shape_data.into_iter().collect(), Vec::new())
+    };
 
-    // allocate objects
-    let mut objects = DenseSlotMap::default();
-    let num_objects = stream.load_as::<u32, usize>()?;
-    objects.reserve(num_objects);
     let mut block_assets = HashMap::new();
-    let mut all_controller_links = Vec::new();
-    let mut all_keyframe_data = HashMap::new();
-    let mut all_tked = HashMap::new();
-    let mut all_sed = HashMap::new();
     let mut node_names = HashMap::new();
-    // populate objects
-    //
-    for i in 0..num_objects {
-        let ni_type: NiType = stream.load()?;
+    let edited_on_spawn = meshes_edited_on_spawn(&stream);
+    for (i, (key, ni_type)) in stream.objects.iter().enumerate() {
         match ni_type {
             NiType::NiNode(node) => {
-                let name = node.name.clone();
-                let key = objects.insert(NiType::NiNode(node));
-                node_names.insert(key, name);
+                node_names.insert(key, node.name.clone());
             }
             NiType::NiTriShape(trishape) => {
-                let name = trishape.name.clone();
-                let key = objects.insert(NiType::NiTriShape(trishape));
-                node_names.insert(key, name);
+                node_names.insert(key, trishape.name.clone());
             }
             NiType::NiTriShapeData(data) => {
-                if let Some(mesh) = convert_nif_mesh(data) {
+                // blocks of a lenient load can't be found in the bytes again, they convert from
+                // the stream instead
+                let data = shape_data.remove(&key).unwrap_or_else(|| data.into());
+                if let Some(mut mesh) = convert_nif_mesh_ref(data, settings) {
+                    if edited_on_spawn.contains(&key) {
+                        mesh.asset_usage = RenderAssetUsages::default();
+                    }
                     let handle = load_context.add_labeled_asset(format!("mesh_{}", i), mesh);
-                    // Inserting into objects first because we don't have NiKey until we insert it
-                    // into the slotmap, which would mean it's moved so we can't match on it.
-                    let key: NiKey = objects.insert(NiType::Empty);
                     block_assets.insert(key, ConsumedNiType::NiTriShapeData(handle));
                 }
             }
-            NiType::NiKeyframeData(kfd) => {
-                let key: NiKey = objects.insert(NiType::Empty);
-                all_keyframe_data.insert(key, kfd);
-            }
-            NiType::NiKeyframeController(kfc) => {
-                let target_key = kfc.target.key;
-                all_controller_links.push((target_key, kfc));
-                objects.insert(NiType::Empty);
-            }
-            NiType::NiTextKeyExtraData(tked) => {
-                let key: NiKey = objects.insert(NiType::Empty);
-                all_tked.insert(key, tked);
-            }
-            NiType::NiStringExtraData(sed) => {
-                let key: NiKey = objects.insert(NiType::Empty);
-                all_sed.insert(key, sed);
-            }
-
-            _ =>
-            // All NiTypes that can't just create assets and then be discarded, store them
-            // in full
-            {
-                objects.insert(ni_type);
-            }
+            _ => {}
         }
     }
 
-    // allocate roots
-    let mut roots = Vec::new();
-    let num_roots = stream.load_as::<u32, usize>()?;
-    roots.reserve(num_roots);
-
-    // populate roots
-    for _ in 0..num_roots {
-        roots.push(stream.load()?);
-    }
     // Text keys are usually stored on Bip01 or "Root Bone" node, this function extracts the text
     // keys (animation events like footstep sound markers) if it finds any on bip01 or root bone.
     let final_text_keys = 'data_extraction: {
@@ -167,7 +232,7 @@ pub fn load_nif_bytes(bytes: &[u8], load_context: &mut LoadContext<'_>) -> io::R
         };
 
         // Get the NiNode block using the key
-        let Some(root_node) = objects.get(*key) else {
+        let Some(root_node) = stream.objects.get(*key) else {
             break 'data_extraction Vec::new();
         };
         let root_node = match root_node {
@@ -182,111 +247,178 @@ pub fn load_nif_bytes(bytes: &[u8], load_context: &mut LoadContext<'_>) -> io::R
 
         // Loop through the extra data chain
         while !current_link_key.is_null() {
-            let key_to_lookup = current_link_key;
-            // Check for NiTextKeyExtraData (the target block)
-            if let Some(tked) = all_tked.get(&key_to_lookup) {
+            current_link_key = match stream.objects.get(current_link_key) {
                 // Found the correct TextKey block linked from Bip01/Root Bone.
-                break 'data_extraction tked.keys.clone();
-            }
-
-            // Follow the next link in the chain:
-            current_link_key = if let Some(tked) = all_tked.get(&key_to_lookup) {
-                // This is the TextKey block we just checked (if it wasn't the target, we shouldn't be here)
-                tked.base.next.key
-            } else if let Some(sed) = all_sed.get(&key_to_lookup) {
+                Some(NiType::NiTextKeyExtraData(tked)) => break 'data_extraction tked.keys.clone(),
                 // Found NiStringExtraData, continue the chain
-                sed.base.next.key
-            } else {
+                Some(NiType::NiStringExtraData(sed)) => sed.base.next.key,
                 // Found a block not in our traversal types (chain ends)
-                NiKey::null()
+                _ => NiKey::null(),
             };
-
-            if current_link_key.is_null() {
-                break; // Chain is broken
-            }
         }
 
         Vec::new() // Traversal failed
     };
 
     Ok(Nif {
-        objects,
-        roots,
+        stream,
         block_assets,
-        all_keyframe_data,
-        all_controller_links,
         text_keys: final_text_keys,
         node_names,
+        settings: settings.clone(),
+        diagnostics,
     })
 }
 
-pub fn resolve_nif_path(nif_path: &str) -> String {
-    // Basic cleanup - Needs proper implementation!
-    let cleaned = nif_path.trim().replace('\\', "/");
-    if !cleaned.starts_with("textures/") && !cleaned.is_empty() {
-        format!("textures/{}", cleaned)
-    } else {
-        cleaned
-    }
+/// The shape data whose meshes spawning edits, which has to stay on the CPU: skinned shapes get
+/// joint attributes and shapes that ignore their vertex colors have them removed
+fn meshes_edited_on_spawn(stream: &NiStream) -> HashSet<NiKey> {
+    stream
+        .objects_of_type::<NiTriShape>()
+        .filter(|shape| {
+            !shape.skin_instance.is_null()
+                || shape.properties.iter().any(|property| {
+                    matches!(
+                        stream.objects.get(property.key),
+                        Some(NiType::NiVertexColorProperty(vertex_color_prop))
+                            if vertex_color_prop.source_vertex_mode == SourceVertexMode::Ignore
+                    )
+                })
+        })
+        .map(|shape| shape.geometry_data.key)
+        .collect()
+}
+
+/// Convert shape data that's no longer needed, its arrays are moved into the mesh
+pub fn convert_nif_mesh(data: NiTriShapeData, settings: &NifLoaderSettings) -> Option<Mesh> {
+    convert_nif_mesh_ref(data.into(), settings)
 }
 
-pub fn convert_nif_mesh(data: NiTriShapeData) -> Option<Mesh> {
+/// Convert shape data whose arrays may borrow the file bytes, see
+/// [`NiStream::load_bytes_with_shape_data`]. Borrowed arrays are copied once, straight into the
+/// mesh buffers, and owned ones are moved like in [`convert_nif_mesh`].
+pub fn convert_nif_mesh_ref(
+    data: NiTriShapeDataRef<'_>,
+    settings: &NifLoaderSettings,
+) -> Option<Mesh> {
     // TODO:: not sure what to do with shared normals
-    let NiTriShapeData {
+    let NiTriShapeDataRef {
         base,
         triangles,
         shared_normals: _shared_normals,
     } = data;
-    let NiTriBasedGeomData { base } = base;
-    let vertices = base.vertices;
-    let normals = base.normals;
-    let uvs = base.uv_sets;
-    let indices = triangles;
-    let final_mesh_opt: Option<Mesh>;
-    let flat_indices: Vec<u16> = indices.into_iter().flatten().collect();
-    if !normals.is_empty() {
-        let mut mesh = Mesh::new(
-            PrimitiveTopology::TriangleList,
-            RenderAssetUsages::default(),
-        )
-        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
-
-        // Create the Bevy Mesh
-        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals); // MOVE
-
-        // Insert UVs if they exist
-        if !uvs.is_empty() {
-            mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs); // MOVE
-        }
-        // Insert the final flat indices
-        mesh.insert_indices(Indices::U16(flat_indices));
-
-        final_mesh_opt = Some(mesh);
-    } else {
-        // Fallback: Need to call the helper function, which needs to be updated
-        // to accept the Bevy array types (Vec<[f32; 3]>).
-
+    let vertices = base.vertices.into_owned();
+    let normals = base.normals.into_owned();
+    let (uvs, uvs_1) = first_uv_sets(base.uv_sets, vertices.len());
+    let colors: Vec<Vec4> = base
+        .vertex_colors
+        .iter()
+        .copied()
+        .map(vertex_color_to_linear)
+        .collect();
+    let flat_indices: Vec<u16> = triangles.iter().flatten().copied().collect();
+    if normals.is_empty() && settings.normal_generation == NifNormalGeneration::Flat {
         // We pass references to the moved data's components before they are consumed below.
-        final_mesh_opt = create_mesh_with_flat_normals(
+        return create_mesh_with_flat_normals(
             // Pass the data that contains the vertex position info
             vertices,
             flat_indices,
             if uvs.is_empty() { None } else { Some(&uvs) },
+            if uvs_1.is_empty() { None } else { Some(&uvs_1) },
+            if colors.is_empty() {
+                None
+            } else {
+                Some(&colors)
+            },
+            settings.mesh_asset_usage(),
         );
     }
 
-    // TODO: Add vertex colors (Mesh::ATTRIBUTE_COLOR) if data.has_vertex_colors is true
+    // Smooth normals need indices that stay inside the vertices
+    if normals.is_empty()
+        && settings.normal_generation == NifNormalGeneration::Smooth
+        && flat_indices
+            .iter()
+            .any(|&index| usize::from(index) >= vertices.len())
+    {
+        warn!("Cannot compute smooth normals: out of bounds vertex index.");
+        return None;
+    }
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, settings.mesh_asset_usage())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+
+    // Create the Bevy Mesh
+    let has_normals = !normals.is_empty();
+    if has_normals {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals); // MOVE
+    }
 
-    if let Some(mesh) = final_mesh_opt {
-        Some(mesh)
-    } else {
-        None
+    // Insert UVs if they exist
+    if !uvs.is_empty() {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs); // MOVE
     }
+    if !uvs_1.is_empty() {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_1, uvs_1); // MOVE
+    }
+    if !colors.is_empty() {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors); // MOVE
+    }
+    // Insert the final flat indices
+    mesh.insert_indices(Indices::U16(flat_indices));
+
+    if !has_normals && settings.normal_generation == NifNormalGeneration::Smooth {
+        mesh.compute_smooth_normals();
+    }
+    Some(mesh)
+}
+/// The first two of the back to back uv sets, Bevy meshes only have room for two of them. Owned
+/// sets hand their buffer over to the first set instead of copying it.
+fn first_uv_sets(uv_sets: Cow<'_, [Vec2]>, num_vertices: usize) -> (Vec<Vec2>, Vec<Vec2>) {
+    if num_vertices == 0 || uv_sets.len() < num_vertices {
+        return (Vec::new(), Vec::new());
+    }
+    match uv_sets {
+        Cow::Borrowed(uv_sets) => {
+            let mut sets = uv_sets.chunks_exact(num_vertices).map(Vec::from);
+            (
+                sets.next().unwrap_or_default(),
+                sets.next().unwrap_or_default(),
+            )
+        }
+        Cow::Owned(mut uvs) => {
+            let mut uvs_1 = uvs.split_off(num_vertices);
+            uvs_1.truncate(num_vertices);
+            if uvs_1.len() < num_vertices {
+                uvs_1.clear();
+            }
+            (uvs, uvs_1)
+        }
+    }
+}
+/// NIF vertex colors are stored in sRGB like the rest of the fixed function colors,
+/// Bevy expects linear vertex colors.
+fn vertex_color_to_linear(color: ColorA) -> Vec4 {
+    let to_linear = |c: f32| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    Vec4::new(
+        to_linear(color.x),
+        to_linear(color.y),
+        to_linear(color.z),
+        color.w,
+    )
 }
 fn create_mesh_with_flat_normals(
     original_vertices_nif: Vec<Vec3>,
     original_indices: Vec<u16>,
     original_uvs: Option<&Vec<Vec2>>,
+    original_uvs_1: Option<&Vec<Vec2>>,
+    original_colors: Option<&Vec<Vec4>>,
+    asset_usage: RenderAssetUsages,
 ) -> Option<Mesh> {
     let vertex_count = original_vertices_nif.len();
     if vertex_count == 0 {
@@ -315,6 +447,10 @@ fn create_mesh_with_flat_normals(
     // Only create UV buffer if original UVs were present
     let mut final_uvs: Option<Vec<[f32; 2]>> =
         original_uvs.map(|_| Vec::with_capacity(new_vertex_count));
+    let mut final_uvs_1: Option<Vec<[f32; 2]>> =
+        original_uvs_1.map(|_| Vec::with_capacity(new_vertex_count));
+    let mut final_colors: Option<Vec<[f32; 4]>> =
+        original_colors.map(|_| Vec::with_capacity(new_vertex_count));
 
     for i in 0..num_triangles {
         // Get original vertex indices for this triangle
@@ -382,6 +518,22 @@ fn create_mesh_with_flat_normals(
                 }
             }
         }
+        // Duplicate the second UV set if it exists
+        if let Some(ref mut uvs_out) = final_uvs_1
+            && let Some(uvs_in) = original_uvs_1
+        {
+            for idx in [idx0, idx1, idx2] {
+                uvs_out.push(uvs_in.get(idx).copied().unwrap_or(Vec2::ZERO).to_array());
+            }
+        }
+        // Duplicate vertex colors if they exist, white doesn't tint anything
+        if let Some(ref mut colors_out) = final_colors
+            && let Some(colors_in) = original_colors
+        {
+            for idx in [idx0, idx1, idx2] {
+                colors_out.push(colors_in.get(idx).copied().unwrap_or(Vec4::ONE).to_array());
+            }
+        }
     }
 
     // sanity check
@@ -406,16 +558,19 @@ fn create_mesh_with_flat_normals(
     }
 
     // Create the Bevy Mesh using the generated data
-    let mut mesh = Mesh::new(
-        PrimitiveTopology::TriangleList,
-        RenderAssetUsages::default(),
-    )
-    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, final_vertices) // Use new vertices
-    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, final_normals); // Use new normals
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, asset_usage)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, final_vertices) // Use new vertices
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, final_normals); // Use new normals
 
     if let Some(final_uvs_vec) = final_uvs {
         mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, final_uvs_vec);
     }
+    if let Some(final_uvs_1_vec) = final_uvs_1 {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_1, final_uvs_1_vec);
+    }
+    if let Some(final_colors_vec) = final_colors {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, final_colors_vec);
+    }
 
     mesh.insert_indices(Indices::U16(final_indices)); // Use new sequential indices
 
@@ -427,3 +582,37 @@ fn create_mesh_with_flat_normals(
 
     Some(mesh)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hidden_nodes() {
+        let mut settings = NifLoaderSettings::default();
+        assert!(settings.is_hidden_node("Tri Shadow"));
+        assert!(settings.is_hidden_node("Tri QuadPatch01"));
+        assert!(!settings.is_hidden_node("Tri Shadow 1"));
+        assert!(!settings.is_hidden_node("Bip01"));
+
+        settings.hidden_node_names = vec!["Bip01".to_string()];
+        assert!(settings.is_hidden_node("Bip01"));
+        assert!(!settings.is_hidden_node("Tri Shadow"));
+    }
+
+    #[test]
+    fn test_first_uv_sets() {
+        let sets = [Vec2::X, Vec2::Y, Vec2::ONE, Vec2::ZERO, Vec2::NEG_X];
+        let expected = (vec![Vec2::X, Vec2::Y], vec![Vec2::ONE, Vec2::ZERO]);
+        assert_eq!(first_uv_sets(Cow::Borrowed(&sets), 2), expected);
+        assert_eq!(first_uv_sets(Cow::Owned(sets.to_vec()), 2), expected);
+        assert_eq!(
+            first_uv_sets(Cow::Owned(sets[..3].to_vec()), 2),
+            (vec![Vec2::X, Vec2::Y], Vec::new())
+        );
+        assert_eq!(
+            first_uv_sets(Cow::Borrowed(&sets), 0),
+            (Vec::new(), Vec::new())
+        );
+    }
+}
@@ -50,3 +50,87 @@ macro_rules! flag_props {
     };
 }
 pub(crate) use flag_props;
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[derive(LoadSave, Clone, Debug, PartialEq, SmartDefault)]
+    struct FieldOptions {
+        #[load_save(count = u16)]
+        list: Vec<u32>,
+        #[load_save(bool = u8)]
+        has_extra: bool,
+        #[load_save(bool)]
+        versioned_bool: bool,
+        #[load_save(flag)]
+        optional: Option<u32>,
+        #[load_save(present_if = has_extra)]
+        #[default(7)]
+        extra: u32,
+        #[load_save(since = version::V4_0_0_2)]
+        #[default(5)]
+        newer: u32,
+        #[load_save(until = version::V4_0_0_2)]
+        #[default(9)]
+        older: u32,
+    }
+
+    fn round_trip(value: &FieldOptions, version: u32) -> (usize, FieldOptions) {
+        let mut writer = Writer::new(Vec::new());
+        writer.version = version;
+        writer.save(value).unwrap();
+        let bytes = writer.cursor.into_inner();
+        let mut reader = Reader::new(&bytes);
+        reader.version = version;
+        let loaded = reader.load().unwrap();
+        assert_eq!(reader.position(), bytes.len() as u64);
+        (bytes.len(), loaded)
+    }
+
+    #[test]
+    fn test_load_save_field_options() {
+        let value = FieldOptions {
+            list: vec![1, 2, 3],
+            has_extra: true,
+            versioned_bool: true,
+            optional: Some(4),
+            extra: 8,
+            newer: 6,
+            older: 10,
+        };
+        // count + list, has_extra, versioned_bool as u32, flag as u32 + value, extra, older
+        let (len, loaded) = round_trip(&value, version::V3_1_0_1);
+        assert_eq!(len, 2 + 12 + 1 + 4 + 4 + 4 + 4 + 4);
+        assert_eq!(
+            loaded,
+            FieldOptions {
+                newer: 5,
+                ..value.clone()
+            }
+        );
+        // Both version bounds are inclusive
+        let (len, loaded) = round_trip(&value, version::V4_0_0_2);
+        assert_eq!(len, 2 + 12 + 1 + 4 + 4 + 4 + 4 + 4 + 4);
+        assert_eq!(loaded, value);
+        // versioned_bool and the flag become u8, older is gone
+        let (len, loaded) = round_trip(&value, version::V4_0_0_2 + 1);
+        assert_eq!(len, 2 + 12 + 1 + 1 + 1 + 4 + 4 + 4);
+        assert_eq!(
+            loaded,
+            FieldOptions {
+                older: 9,
+                ..value.clone()
+            }
+        );
+
+        let value = FieldOptions {
+            has_extra: false,
+            optional: None,
+            ..value
+        };
+        let (len, loaded) = round_trip(&value, version::V4_0_0_2);
+        assert_eq!(len, 2 + 12 + 1 + 4 + 4 + 4 + 4);
+        assert_eq!(loaded, FieldOptions { extra: 7, ..value });
+    }
+}
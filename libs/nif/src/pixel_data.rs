@@ -0,0 +1,684 @@
+//! Decoding and encoding of textures embedded in a NIF as `NiPixelData`.
+//!
+//! Every supported format is decoded to tightly packed RGBA8, with the mip levels one after
+//! the other, ready to become a GPU texture. Going the other way, RGBA8 pixels are encoded as
+//! RGB, RGBA or palettized pixel data with a generated mip chain.
+
+use bevy_log::warn;
+
+// internal imports
+use crate::prelude::*;
+
+/// An `NiPixelData` decoded to RGBA8
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DecodedPixelData {
+    pub width: u32,
+    pub height: u32,
+    pub mip_level_count: u32,
+    /// Every mip level, largest first
+    pub rgba: Vec<u8>,
+}
+
+/// Decode every mip level of `pixel_data`. Palettized formats read their colors from
+/// `palette`, the block `NiPixelData::palette` links to.
+pub fn decode_pixel_data(
+    pixel_data: &NiPixelData,
+    palette: Option<&NiPalette>,
+) -> io::Result<DecodedPixelData> {
+    let Some(&[width, height, _]) = pixel_data.mipmaps.first() else {
+        return Reader::error("NiPixelData without mip levels");
+    };
+    let mut decoded = DecodedPixelData {
+        width,
+        height,
+        mip_level_count: 0,
+        rgba: Vec::new(),
+    };
+    for (&[level_width, level_height, offset], level) in pixel_data.mipmaps.iter().zip(0u32..) {
+        // The GPU expects every level to be half the size of the one above
+        if level_width != mip_extent(width, level) || level_height != mip_extent(height, level) {
+            warn!(
+                "NiPixelData mip level {level} is {level_width}x{level_height}, dropping it and \
+                 every smaller level"
+            );
+            break;
+        }
+        let Some(data) = pixel_data.pixel_data.get(offset as usize..) else {
+            return Reader::error("NiPixelData mip level starts past the pixel data");
+        };
+        decode_level(
+            pixel_data,
+            palette,
+            level_width,
+            level_height,
+            data,
+            &mut decoded.rgba,
+        )?;
+        decoded.mip_level_count += 1;
+    }
+    Ok(decoded)
+}
+
+fn mip_extent(extent: u32, level: u32) -> u32 {
+    extent.checked_shr(level).unwrap_or(0).max(1)
+}
+
+fn decode_level(
+    pixel_data: &NiPixelData,
+    palette: Option<&NiPalette>,
+    width: u32,
+    height: u32,
+    data: &[u8],
+    rgba: &mut Vec<u8>,
+) -> io::Result<()> {
+    let format = &pixel_data.pixel_format;
+    match format.pixel_format {
+        PixelFormat::RGB | PixelFormat::RGBA => decode_masked(format, width, height, data, rgba),
+        PixelFormat::PAL | PixelFormat::PALAlpha => {
+            let Some(palette) = palette else {
+                return Reader::error("Palettized NiPixelData without an NiPalette");
+            };
+            decode_palettized(format, palette, width, height, data, rgba)
+        }
+        PixelFormat::Compress1 => decode_blocks(BlockFormat::Bc1, width, height, data, rgba),
+        PixelFormat::Compress3 => decode_blocks(BlockFormat::Bc2, width, height, data, rgba),
+        PixelFormat::Compress5 => decode_blocks(BlockFormat::Bc3, width, height, data, rgba),
+        unsupported => Reader::error(format!("Unsupported NiPixelData format {unsupported:?}")),
+    }
+}
+
+/// Each pixel is a little endian integer, the color masks pick the channels out of it
+fn decode_masked(
+    format: &NiPixelFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+    rgba: &mut Vec<u8>,
+) -> io::Result<()> {
+    let bytes_per_pixel = format.bits_per_pixel as usize / 8;
+    if !(1..=4).contains(&bytes_per_pixel) {
+        return Reader::error(format!(
+            "Unsupported NiPixelData bit depth {}",
+            format.bits_per_pixel
+        ));
+    }
+    let pixel_count = width as usize * height as usize;
+    let Some(data) = data.get(..pixel_count * bytes_per_pixel) else {
+        return Reader::error("NiPixelData mip level is cut short");
+    };
+    let [red_mask, green_mask, blue_mask, alpha_mask] = format.color_masks;
+    let alpha_mask = match format.pixel_format {
+        PixelFormat::RGBA => alpha_mask,
+        _ => 0,
+    };
+    for pixel in data.chunks_exact(bytes_per_pixel) {
+        let mut value = [0; 4];
+        value[..bytes_per_pixel].copy_from_slice(pixel);
+        let value = u32::from_le_bytes(value);
+        rgba.extend([
+            mask_channel(value, red_mask),
+            mask_channel(value, green_mask),
+            mask_channel(value, blue_mask),
+            if alpha_mask == 0 {
+                u8::MAX
+            } else {
+                mask_channel(value, alpha_mask)
+            },
+        ]);
+    }
+    Ok(())
+}
+
+/// Extract the bits of `mask` and widen or narrow them to 8 bits
+fn mask_channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let bits = mask.count_ones();
+    let channel = (value & mask) >> mask.trailing_zeros();
+    let channel = if bits >= 8 {
+        channel >> (bits - 8)
+    } else {
+        channel * 255 / ((1 << bits) - 1)
+    };
+    to_u8(channel)
+}
+
+/// One byte per pixel indexing the palette
+fn decode_palettized(
+    format: &NiPixelFormat,
+    palette: &NiPalette,
+    width: u32,
+    height: u32,
+    data: &[u8],
+    rgba: &mut Vec<u8>,
+) -> io::Result<()> {
+    if format.bits_per_pixel != 8 {
+        return Reader::error(format!(
+            "Unsupported palettized NiPixelData bit depth {}",
+            format.bits_per_pixel
+        ));
+    }
+    let pixel_count = width as usize * height as usize;
+    let Some(data) = data.get(..pixel_count) else {
+        return Reader::error("NiPixelData mip level is cut short");
+    };
+    let has_alpha = format.pixel_format == PixelFormat::PALAlpha || palette.has_alpha;
+    for &index in data {
+        let Some(&[red, green, blue, alpha]) = palette.palettes.get(usize::from(index)) else {
+            return Reader::error("NiPixelData indexes past the end of its NiPalette");
+        };
+        rgba.extend([red, green, blue, if has_alpha { alpha } else { u8::MAX }]);
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BlockFormat {
+    /// DXT1, 4x4 pixels in 8 bytes of color with 1 bit alpha
+    Bc1,
+    /// DXT3, 4 bit explicit alpha followed by a color block
+    Bc2,
+    /// DXT5, interpolated alpha followed by a color block
+    Bc3,
+}
+
+/// 4x4 pixel blocks, partial blocks along the right and bottom edges are cropped
+fn decode_blocks(
+    block_format: BlockFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+    rgba: &mut Vec<u8>,
+) -> io::Result<()> {
+    let block_size = match block_format {
+        BlockFormat::Bc1 => 8,
+        BlockFormat::Bc2 | BlockFormat::Bc3 => 16,
+    };
+    let (width, height) = (width as usize, height as usize);
+    let blocks_wide = width.div_ceil(4);
+    let blocks_high = height.div_ceil(4);
+    let Some(data) = data.get(..blocks_wide * blocks_high * block_size) else {
+        return Reader::error("NiPixelData mip level is cut short");
+    };
+    let level_start = rgba.len();
+    rgba.resize(level_start + width * height * 4, 0);
+    let level = &mut rgba[level_start..];
+    for (block_index, block) in data.chunks_exact(block_size).enumerate() {
+        let texels = match block_format {
+            BlockFormat::Bc1 => decode_color_block(block, true),
+            BlockFormat::Bc2 => {
+                let mut texels = decode_color_block(&block[8..], false);
+                // Two texels per byte, low nibble first
+                for (texel_pair, &alpha) in texels.chunks_exact_mut(2).zip(&block[..8]) {
+                    texel_pair[0][3] = (alpha & 0xF) * 17;
+                    texel_pair[1][3] = (alpha >> 4) * 17;
+                }
+                texels
+            }
+            BlockFormat::Bc3 => {
+                let mut texels = decode_color_block(&block[8..], false);
+                let alphas = decode_alpha_block(&block[..8]);
+                for (texel, alpha) in texels.iter_mut().zip(alphas) {
+                    texel[3] = alpha;
+                }
+                texels
+            }
+        };
+        let block_x = (block_index % blocks_wide) * 4;
+        let block_y = (block_index / blocks_wide) * 4;
+        for (texel_index, texel) in texels.iter().enumerate() {
+            let x = block_x + texel_index % 4;
+            let y = block_y + texel_index / 4;
+            if x < width && y < height {
+                let start = (y * width + x) * 4;
+                level[start..start + 4].copy_from_slice(texel);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Two RGB565 endpoints and 2 bit indices. Only BC1 uses the 3 color + transparent mode.
+fn decode_color_block(block: &[u8], allow_transparent: bool) -> [[u8; 4]; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let (end0, end1) = (rgb565(color0), rgb565(color1));
+    let mix = |weight0: u32, weight1: u32| {
+        let total = weight0 + weight1;
+        [
+            to_u8((end0[0] * weight0 + end1[0] * weight1) / total),
+            to_u8((end0[1] * weight0 + end1[1] * weight1) / total),
+            to_u8((end0[2] * weight0 + end1[2] * weight1) / total),
+            u8::MAX,
+        ]
+    };
+    let palette = if color0 > color1 || !allow_transparent {
+        [mix(1, 0), mix(0, 1), mix(2, 1), mix(1, 2)]
+    } else {
+        [mix(1, 0), mix(0, 1), mix(1, 1), [0; 4]]
+    };
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|texel| palette[((indices >> (texel * 2)) & 0b11) as usize])
+}
+
+/// Two 8 bit endpoints and 3 bit indices
+fn decode_alpha_block(block: &[u8]) -> [u8; 16] {
+    let alpha0 = u32::from(block[0]);
+    let alpha1 = u32::from(block[1]);
+    let mut palette = [0; 8];
+    for (entry, index) in palette.iter_mut().zip(0u32..) {
+        let alpha = match index {
+            0 => alpha0,
+            1 => alpha1,
+            _ if alpha0 > alpha1 => ((8 - index) * alpha0 + (index - 1) * alpha1) / 7,
+            6 => 0,
+            7 => 255,
+            _ => ((6 - index) * alpha0 + (index - 1) * alpha1) / 5,
+        };
+        *entry = to_u8(alpha);
+    }
+    // Two runs of 8 texels, 24 bits each
+    let mut texel_alphas = [0; 16];
+    for (texels, bits) in texel_alphas
+        .chunks_exact_mut(8)
+        .zip(block[2..8].chunks_exact(3))
+    {
+        let indices = u32::from_le_bytes([bits[0], bits[1], bits[2], 0]);
+        for (texel, alpha) in texels.iter_mut().enumerate() {
+            *alpha = palette[((indices >> (texel * 3)) & 0b111) as usize];
+        }
+    }
+    texel_alphas
+}
+
+fn rgb565(color: u16) -> [u32; 3] {
+    let color = u32::from(color);
+    [
+        ((color >> 11) & 0x1F) * 255 / 31,
+        ((color >> 5) & 0x3F) * 255 / 63,
+        (color & 0x1F) * 255 / 31,
+    ]
+}
+
+fn to_u8(value: u32) -> u8 {
+    u8::try_from(value).unwrap_or(u8::MAX)
+}
+
+/// The layout [`encode_pixel_data`] stores pixels in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PixelDataEncoding {
+    /// 24 bit RGB, alpha is dropped
+    Rgb,
+    /// 32 bit RGBA
+    #[default]
+    Rgba,
+    /// 8 bit indices into an opaque 256 color `NiPalette`
+    Palettized,
+    /// 8 bit indices into a 256 color `NiPalette` with alpha
+    PalettizedAlpha,
+}
+
+/// An `NiPixelData` built by [`encode_pixel_data`] and the palette it goes with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncodedPixelData {
+    pub pixel_data: NiPixelData,
+    pub palette: Option<NiPalette>,
+}
+
+impl EncodedPixelData {
+    /// Insert the pixel data and its palette into `stream`, linking the two. An
+    /// `NiSourceTexture` uses it through `TextureSource::Internal` with the returned link.
+    pub fn insert_into(self, stream: &mut NiStream) -> NiLink<NiPixelData> {
+        let Self {
+            mut pixel_data,
+            palette,
+        } = self;
+        if let Some(palette) = palette {
+            pixel_data.palette = stream.insert(palette);
+        }
+        stream.insert(pixel_data)
+    }
+}
+
+/// Encode `width` x `height` tightly packed RGBA8 pixels as an `NiPixelData`.
+///
+/// Every mip level down to 1x1 is generated by averaging blocks of 2x2 pixels, palettized
+/// encodings share one median cut palette between all levels.
+pub fn encode_pixel_data(
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+    encoding: PixelDataEncoding,
+) -> io::Result<EncodedPixelData> {
+    if width == 0 || height == 0 || rgba.len() != width as usize * height as usize * 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} bytes aren't {width}x{height} RGBA8 pixels", rgba.len()),
+        ));
+    }
+    let levels = build_mip_chain(width, height, rgba);
+    let has_alpha = encoding == PixelDataEncoding::PalettizedAlpha;
+    let palette = match encoding {
+        PixelDataEncoding::Palettized | PixelDataEncoding::PalettizedAlpha => {
+            Some(build_palette(&levels[0], has_alpha))
+        }
+        PixelDataEncoding::Rgb | PixelDataEncoding::Rgba => None,
+    };
+    let pixel_format = match encoding {
+        PixelDataEncoding::Rgb => NiPixelFormat {
+            pixel_format: PixelFormat::RGB,
+            color_masks: [0x0000_00FF, 0x0000_FF00, 0x00FF_0000, 0],
+            bits_per_pixel: 24,
+            ..default()
+        },
+        PixelDataEncoding::Rgba => NiPixelFormat {
+            pixel_format: PixelFormat::RGBA,
+            color_masks: [0x0000_00FF, 0x0000_FF00, 0x00FF_0000, 0xFF00_0000],
+            bits_per_pixel: 32,
+            ..default()
+        },
+        PixelDataEncoding::Palettized => NiPixelFormat {
+            pixel_format: PixelFormat::PAL,
+            bits_per_pixel: 8,
+            ..default()
+        },
+        PixelDataEncoding::PalettizedAlpha => NiPixelFormat {
+            pixel_format: PixelFormat::PALAlpha,
+            bits_per_pixel: 8,
+            ..default()
+        },
+    };
+
+    let mut mipmaps = Vec::with_capacity(levels.len());
+    let mut data = Vec::new();
+    let mut palette_indices = HashMap::new();
+    for level in &levels {
+        let offset = u32::try_from(data.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Pixel data is over 4 GiB"))?;
+        mipmaps.push([level.width, level.height, offset]);
+        for pixel in level.rgba.chunks_exact(4) {
+            match (&palette, encoding) {
+                (Some(palette), _) => {
+                    let color = palette_color(pixel, has_alpha);
+                    let index = *palette_indices
+                        .entry(color)
+                        .or_insert_with(|| nearest_palette_index(palette, color));
+                    data.push(index);
+                }
+                (None, PixelDataEncoding::Rgb) => data.extend_from_slice(&pixel[..3]),
+                (None, _) => data.extend_from_slice(pixel),
+            }
+        }
+    }
+
+    Ok(EncodedPixelData {
+        pixel_data: NiPixelData {
+            pixel_stride: pixel_format.bits_per_pixel / 8,
+            pixel_format,
+            mipmaps,
+            pixel_data: data,
+            ..default()
+        },
+        palette: palette.map(|palettes| NiPalette {
+            has_alpha,
+            palettes,
+            ..default()
+        }),
+    })
+}
+
+struct MipLevel {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+fn build_mip_chain(width: u32, height: u32, rgba: &[u8]) -> Vec<MipLevel> {
+    let mut levels = vec![MipLevel {
+        width,
+        height,
+        rgba: rgba.to_vec(),
+    }];
+    while let Some(level) = levels.last()
+        && (level.width > 1 || level.height > 1)
+    {
+        levels.push(downsample(level));
+    }
+    levels
+}
+
+/// Average every 2x2 block, odd edges repeat their last row or column
+fn downsample(level: &MipLevel) -> MipLevel {
+    let width = (level.width / 2).max(1);
+    let height = (level.height / 2).max(1);
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0u32; 4];
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let source_x = (x * 2 + dx).min(level.width - 1) as usize;
+                let source_y = (y * 2 + dy).min(level.height - 1) as usize;
+                let start = (source_y * level.width as usize + source_x) * 4;
+                for (total, &value) in sum.iter_mut().zip(&level.rgba[start..start + 4]) {
+                    *total += u32::from(value);
+                }
+            }
+            rgba.extend(sum.map(|total| to_u8((total + 2) / 4)));
+        }
+    }
+    MipLevel {
+        width,
+        height,
+        rgba,
+    }
+}
+
+/// The color a pixel is matched against the palette with, opaque palettes ignore alpha
+fn palette_color(pixel: &[u8], has_alpha: bool) -> [u8; 4] {
+    let alpha = if has_alpha { pixel[3] } else { u8::MAX };
+    [pixel[0], pixel[1], pixel[2], alpha]
+}
+
+/// Median cut: keep splitting the box of colors with the widest channel at its median until
+/// there are 256 boxes, each box becomes its average color. Padded to the 256 entries an
+/// `NiPalette` has.
+fn build_palette(level: &MipLevel, has_alpha: bool) -> Vec<[u8; 4]> {
+    let mut histogram: HashMap<[u8; 4], u64> = HashMap::new();
+    for pixel in level.rgba.chunks_exact(4) {
+        *histogram
+            .entry(palette_color(pixel, has_alpha))
+            .or_default() += 1;
+    }
+    let mut boxes: Vec<Vec<([u8; 4], u64)>> = vec![histogram.into_iter().collect()];
+    while boxes.len() < 256 {
+        let Some((box_index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(box_index, colors)| (box_index, widest_channel(colors)))
+            .max_by_key(|(_, (_, range))| *range)
+            .map(|(box_index, (channel, _))| (box_index, channel))
+        else {
+            break;
+        };
+        let mut colors = boxes.swap_remove(box_index);
+        colors.sort_unstable_by_key(|(color, _)| color[channel]);
+        let total: u64 = colors.iter().map(|(_, count)| count).sum();
+        let mut running = 0;
+        let median = colors
+            .iter()
+            .position(|(_, count)| {
+                running += count;
+                running * 2 >= total
+            })
+            .map_or(1, |index| index + 1)
+            .clamp(1, colors.len() - 1);
+        let upper = colors.split_off(median);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+    let mut palette: Vec<[u8; 4]> = boxes.iter().map(|colors| average_color(colors)).collect();
+    palette.resize(256, [0, 0, 0, u8::MAX]);
+    palette
+}
+
+/// The channel with the largest spread of values and that spread
+fn widest_channel(colors: &[([u8; 4], u64)]) -> (usize, u8) {
+    (0..4)
+        .map(|channel| {
+            let values = colors.iter().map(|(color, _)| color[channel]);
+            let min = values.clone().min().unwrap_or_default();
+            let max = values.max().unwrap_or_default();
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or_default()
+}
+
+fn average_color(colors: &[([u8; 4], u64)]) -> [u8; 4] {
+    let mut sum = [0u64; 4];
+    let mut total = 0;
+    for (color, count) in colors {
+        for (channel_sum, &value) in sum.iter_mut().zip(color) {
+            *channel_sum += u64::from(value) * count;
+        }
+        total += count;
+    }
+    sum.map(|channel_sum| u8::try_from(channel_sum / total.max(1)).unwrap_or(u8::MAX))
+}
+
+fn nearest_palette_index(palette: &[[u8; 4]], color: [u8; 4]) -> u8 {
+    let distance = |entry: &[u8; 4]| -> u32 {
+        entry
+            .iter()
+            .zip(color)
+            .map(|(&a, b)| u32::from(a.abs_diff(b)).pow(2))
+            .sum()
+    };
+    let index = palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, entry)| distance(entry))
+        .map_or(0, |(index, _)| index);
+    u8::try_from(index).unwrap_or(u8::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixel_data_round_trip() {
+        let (width, height) = (4, 2);
+        let rgba: Vec<u8> = (0..width * height * 4).map(|i| to_u8(i * 7)).collect();
+        for encoding in [
+            PixelDataEncoding::Rgb,
+            PixelDataEncoding::Rgba,
+            PixelDataEncoding::Palettized,
+            PixelDataEncoding::PalettizedAlpha,
+        ] {
+            let mut stream = NiStream::new();
+            let link = encode_pixel_data(width, height, &rgba, encoding)
+                .unwrap()
+                .insert_into(&mut stream);
+            stream.roots.push(link.cast());
+
+            let stream = NiStream::from_bytes(&stream.save_bytes().unwrap()).unwrap();
+            let pixel_data = stream.objects_of_type::<NiPixelData>().next().unwrap();
+            let palette = stream.get(pixel_data.palette);
+            let decoded = decode_pixel_data(pixel_data, palette).unwrap();
+
+            // 4x2, 2x1 and 1x1
+            assert_eq!(decoded.mip_level_count, 3);
+            for (decoded, original) in decoded.rgba.chunks_exact(4).zip(rgba.chunks_exact(4)) {
+                match encoding {
+                    PixelDataEncoding::Rgb | PixelDataEncoding::Palettized => {
+                        assert_eq!(decoded[..3], original[..3]);
+                        assert_eq!(decoded[3], u8::MAX);
+                    }
+                    _ => assert_eq!(decoded, original),
+                }
+            }
+        }
+    }
+
+    /// Decode a single 4x4 block of a compressed format
+    fn decode_block(pixel_format: PixelFormat, block: &[u8]) -> Vec<[u8; 4]> {
+        let pixel_data = NiPixelData {
+            pixel_format: NiPixelFormat {
+                pixel_format,
+                ..default()
+            },
+            mipmaps: vec![[4, 4, 0]],
+            pixel_data: block.to_vec(),
+            ..default()
+        };
+        let decoded = decode_pixel_data(&pixel_data, None).unwrap();
+        decoded
+            .rgba
+            .chunks_exact(4)
+            .map(|texel| [texel[0], texel[1], texel[2], texel[3]])
+            .collect()
+    }
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    #[test]
+    fn test_decode_dxt1() {
+        // Red then blue endpoints, texels 0 to 3 use indices 0 to 3, the rest index 0
+        let texels = decode_block(
+            PixelFormat::Compress1,
+            &[0x00, 0xF8, 0x1F, 0x00, 0xE4, 0, 0, 0],
+        );
+        assert_eq!(
+            texels[..4],
+            [RED, BLUE, [170, 0, 85, 255], [85, 0, 170, 255]]
+        );
+        assert!(texels[4..].iter().all(|&texel| texel == RED));
+        // Swapped endpoints switch to three colors and transparent black
+        let texels = decode_block(
+            PixelFormat::Compress1,
+            &[0x1F, 0x00, 0x00, 0xF8, 0xE4, 0, 0, 0],
+        );
+        assert_eq!(texels[..4], [BLUE, RED, [127, 0, 127, 255], [0; 4]]);
+    }
+
+    #[test]
+    fn test_decode_dxt3() {
+        // Explicit alpha, low nibble first, then a color block whose endpoints would make a BC1
+        // block transparent. Texel 0 uses index 3, the rest index 0.
+        let mut block = vec![0xF0, 0x84, 0, 0, 0, 0, 0, 0];
+        block.extend([0x1F, 0x00, 0x00, 0xF8, 0x03, 0, 0, 0]);
+        let texels = decode_block(PixelFormat::Compress3, &block);
+        assert_eq!(
+            texels[..4],
+            [
+                [170, 0, 85, 0],
+                [0, 0, 255, 255],
+                [0, 0, 255, 68],
+                [0, 0, 255, 136]
+            ]
+        );
+        assert!(texels[4..].iter().all(|&texel| texel == [0, 0, 255, 0]));
+    }
+
+    #[test]
+    fn test_decode_dxt5() {
+        // Alpha endpoints 255 and 0 interpolate 6 values, texels 0 to 3 use indices 0, 1, 2
+        // and 7, the rest index 0. The color block is all white.
+        let mut block = vec![255, 0, 0x88, 0x0E, 0, 0, 0, 0];
+        block.extend([0xFF, 0xFF, 0x00, 0x00, 0, 0, 0, 0]);
+        let texels = decode_block(PixelFormat::Compress5, &block);
+        let alphas: Vec<_> = texels.iter().map(|texel| texel[3]).collect();
+        assert_eq!(alphas[..4], [255, 0, 218, 36]);
+        assert!(alphas[4..].iter().all(|&alpha| alpha == 255));
+        assert!(texels.iter().all(|texel| texel[..3] == [255; 3]));
+        // A lower first endpoint interpolates 4 values, index 6 is 0 and 7 is 255
+        block[..8].copy_from_slice(&[0, 255, 0x88, 0x0C, 0, 0, 0, 0]);
+        let texels = decode_block(PixelFormat::Compress5, &block);
+        let alphas: Vec<_> = texels[..4].iter().map(|texel| texel[3]).collect();
+        assert_eq!(alphas, [0, 255, 51, 0]);
+    }
+}
@@ -13,6 +13,7 @@ mod niautonormalparticles;
 mod niautonormalparticlesdata;
 mod niavobject;
 mod nibillboardnode;
+mod niblockindex;
 mod nibltsource;
 mod nibound;
 mod niboundingvolume;
@@ -43,6 +44,7 @@ mod nigeometry;
 mod nigeometrydata;
 mod nigeommorphercontroller;
 mod nigravity;
+mod niheader;
 mod nikeyframecontroller;
 mod nikeyframedata;
 mod nikeyframemanager;
@@ -141,6 +143,7 @@ pub use niautonormalparticles::*;
 pub use niautonormalparticlesdata::*;
 pub use niavobject::*;
 pub use nibillboardnode::*;
+pub use niblockindex::*;
 pub use nibltsource::*;
 pub use nibound::*;
 pub use niboundingvolume::*;
@@ -171,6 +174,7 @@ pub use nigeometry::*;
 pub use nigeometrydata::*;
 pub use nigeommorphercontroller::*;
 pub use nigravity::*;
+pub use niheader::*;
 pub use nikeyframecontroller::*;
 pub use nikeyframedata::*;
 pub use nikeyframemanager::*;
@@ -301,6 +301,16 @@ pub enum PropagateMode {
     Continue = 3,
 }
 
+#[repr(u16)]
+#[derive(LoadSave, NoUninit, Clone, Copy, Debug, Eq, Hash, PartialEq, Default)]
+pub enum BillboardMode {
+    #[default]
+    AlwaysFaceCamera = 0,
+    RotateAboutUp = 1,
+    RigidFaceCamera = 2,
+    AlwaysFaceCenter = 3,
+}
+
 #[repr(u16)]
 #[derive(LoadSave, NoUninit, Clone, Copy, Debug, Eq, Hash, PartialEq, Default)]
 pub enum Axis {
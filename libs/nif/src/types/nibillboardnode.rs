@@ -19,3 +19,9 @@ impl Save for NiBillboardNode {
         Ok(())
     }
 }
+
+impl NiBillboardNode {
+    flag_props! {
+        billboard_mode @ (mask = 0x0060, pos = 5) -> BillboardMode,
+    }
+}
@@ -0,0 +1,235 @@
+// rust std imports
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+// external imports
+use bstr::BStr;
+
+// internal imports
+use crate::prelude::*;
+
+/// Where each block of a file is, so single blocks can be decoded without the rest. See
+/// [`NiStream::load_bytes_indexed`].
+#[derive(Clone, Default)]
+pub struct NiBlockIndex {
+    bytes: Arc<[u8]>,
+    blocks: Vec<NiBlockEntry>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NiBlockEntry {
+    pub type_name: BString,
+    pub range: Range<u64>,
+}
+
+impl fmt::Debug for NiBlockIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NiBlockIndex")
+            .field("len", &self.bytes.len())
+            .field("blocks", &self.blocks)
+            .finish()
+    }
+}
+
+impl NiBlockIndex {
+    pub fn blocks(&self) -> &[NiBlockEntry] {
+        &self.blocks
+    }
+
+    /// The type name of block `index`
+    pub fn type_name(&self, index: usize) -> Option<&BStr> {
+        self.blocks
+            .get(index)
+            .map(|block| block.type_name.as_bstr())
+    }
+
+    /// The indices of the blocks with the given type name
+    pub fn blocks_of_type<'a>(&'a self, type_name: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(move |(_, block)| block.type_name == type_name)
+            .map(|(index, _)| index)
+    }
+}
+
+impl NiStream {
+    /// Index a NIF like [`Self::load_bytes_indexed`].
+    pub fn from_bytes_indexed(bytes: impl Into<Arc<[u8]>>) -> Result<Self, NifError> {
+        let mut stream = Self::new();
+        stream.load_bytes_indexed(bytes)?;
+        Ok(stream)
+    }
+
+    /// Walk the blocks of a NIF once, recording the type name and byte range of each, without
+    /// keeping them. Blocks are then decoded one at a time with [`Self::load_block`], and the
+    /// roots link to them by block index. Vertex and pixel data is skipped over rather than read,
+    /// which makes this much cheaper than [`Self::load_bytes`] for tools that only look at a few
+    /// blocks. `objects` stays empty, and the stream can't be saved.
+    pub fn load_bytes_indexed(&mut self, bytes: impl Into<Arc<[u8]>>) -> Result<(), NifError> {
+        let bytes = bytes.into();
+        let mut stream = Reader::new(&bytes);
+        stream.limits = self.limits;
+        stream.encoding = self.encoding.resolve(&bytes, self.limits);
+
+        // validate header and version
+        self.header = load_header(&mut stream)?;
+
+        // record where each block starts and ends
+        let num_objects = self.header.num_blocks as usize;
+        let mut blocks = Vec::with_capacity(num_objects);
+        stream.skip_bulk_data = true;
+        for block_index in 0..num_objects {
+            let start = stream.position();
+            let ni_type = load_block(&mut stream, block_index, num_objects)?;
+            blocks.push(NiBlockEntry {
+                type_name: ni_type.type_name().into(),
+                range: start..stream.position(),
+            });
+        }
+
+        let roots = stream.load()?;
+        // the reader borrows the bytes the index takes over
+        drop(stream);
+        self.objects.clear();
+        self.roots = roots;
+        self.index = Some(NiBlockIndex { bytes, blocks });
+        Ok(())
+    }
+
+    /// Decode block `index` of an indexed stream. `None` when the stream isn't indexed, there's
+    /// no such block, or it isn't a `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use nif::*;
+    ///
+    /// let stream = NiStream::from_bytes_indexed(std::fs::read("model.nif")?)?;
+    ///
+    /// for root in &stream.roots {
+    ///     let Some(index) = stream.block_index_of(*root) else { continue };
+    ///     if let Some(node) = stream.load_block::<NiNode>(index)? {
+    ///         println!("{} has {} children", node.name, node.children.len());
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn load_block<T>(&self, index: usize) -> Result<Option<T>, NifError>
+    where
+        T: TryFrom<NiType>,
+    {
+        let Some(block_index) = &self.index else {
+            return Ok(None);
+        };
+        let Some(block) = block_index.blocks.get(index) else {
+            return Ok(None);
+        };
+        let mut stream = Reader::new(&block_index.bytes);
+        stream.version = self.header.version;
+        stream.user_version = self.header.user_version;
+        stream.limits = self.limits;
+        stream.encoding = self.encoding.encoding();
+        stream.set_position(block.range.start)?;
+        let ni_type = load_block(&mut stream, index, block_index.blocks.len())?;
+        Ok(ni_type.try_into().ok())
+    }
+
+    /// Decode block `index` of an indexed stream like [`Self::load_block`], but with its vertex and
+    /// triangle arrays borrowed from the indexed bytes where they're aligned, for converting many
+    /// shapes to meshes without copying every array twice. `None` when it isn't an
+    /// `NiTriShapeData`.
+    pub fn load_tri_shape_data_ref(
+        &self,
+        index: usize,
+    ) -> Result<Option<NiTriShapeDataRef<'_>>, NifError> {
+        let Some(block_index) = &self.index else {
+            return Ok(None);
+        };
+        let Some(block) = block_index
+            .blocks
+            .get(index)
+            .filter(|block| block.type_name == "NiTriShapeData")
+        else {
+            return Ok(None);
+        };
+        NiTriShapeDataRef::load_block(&block_index.bytes, self, index, block.range.start).map(Some)
+    }
+
+    /// The block index a link of an indexed stream points to, like the links of its roots and of
+    /// blocks decoded by [`Self::load_block`]
+    pub fn block_index_of<T>(&self, link: NiLink<T>) -> Option<usize> {
+        let block_index = self.index.as_ref()?;
+        if link.is_null() {
+            return None;
+        }
+        usize::try_from(link_index(link.key))
+            .ok()
+            .filter(|&index| index < block_index.blocks.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_block_from_index() {
+        let mut stream = test_shape_stream();
+        let root = stream.insert(NiNode {
+            children: vec![stream.roots[0].cast()],
+            ..default()
+        });
+        stream.roots = vec![root.cast()];
+        let bytes = stream.save_bytes().unwrap();
+
+        let mut indexed = NiStream::from_bytes_indexed(bytes).unwrap();
+        assert!(indexed.objects.is_empty());
+        assert!(indexed.save_bytes().is_err());
+        let index = indexed.block_index_of(indexed.roots[0]).unwrap();
+        let node = indexed.load_block::<NiNode>(index).unwrap().unwrap();
+        let index = indexed.block_index_of(node.children[0]).unwrap();
+        let shape = indexed.load_block::<NiTriShape>(index).unwrap().unwrap();
+        let index = indexed.block_index_of(shape.geometry_data).unwrap();
+        assert_eq!(
+            indexed.index.as_ref().unwrap().type_name(index).unwrap(),
+            "NiTriShapeData"
+        );
+        let data = indexed
+            .load_block::<NiTriShapeData>(index)
+            .unwrap()
+            .unwrap();
+        assert_eq!(data.vertices, [Vec3::X, Vec3::Y, Vec3::Z]);
+        assert!(indexed.load_block::<NiNode>(index).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_load_tri_shape_data_ref() {
+        let mut stream = test_shape_stream();
+        let bytes = stream.save_bytes().unwrap();
+
+        let indexed = NiStream::from_bytes_indexed(bytes.clone()).unwrap();
+        let index = indexed.index.as_ref().unwrap();
+        let index = index.blocks_of_type("NiTriShapeData").next().unwrap();
+        let data = indexed.load_tri_shape_data_ref(index).unwrap().unwrap();
+        assert_eq!(*data.base.vertices, [Vec3::X, Vec3::Y, Vec3::Z]);
+        assert_eq!(*data.triangles, [[0, 1, 2]]);
+        assert_eq!(
+            data.into_owned(),
+            indexed
+                .load_block::<NiTriShapeData>(index)
+                .unwrap()
+                .unwrap()
+        );
+
+        let mut loaded = NiStream::new();
+        let shape_data = loaded.load_bytes_with_shape_data(&bytes).unwrap();
+        assert_eq!(shape_data.len(), 1);
+        let (key, data) = shape_data.into_iter().next().unwrap();
+        assert_eq!(
+            Some(&NiType::NiTriShapeData(data.into_owned())),
+            loaded.objects.get(key)
+        );
+    }
+}
@@ -31,7 +31,7 @@ impl Load for NiBoundingVolume {
             BoundType::Box => BoundData::NiBoxBV(stream.load()?),
             BoundType::Sphere => BoundData::NiSphereBV(stream.load()?),
             BoundType::Union => BoundData::NiUnionBV(stream.load()?),
-            _ => Reader::error(format!("Invalid BoundType: {bound_type:?}"))?,
+            _ => Reader::invalid_enum_value(format!("Invalid BoundType: {bound_type:?}"))?,
         };
         Ok(Self { bound_data })
     }
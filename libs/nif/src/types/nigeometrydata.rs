@@ -1,3 +1,6 @@
+// rust std imports
+use std::borrow::Cow;
+
 // internal imports
 use crate::prelude::*;
 
@@ -13,24 +16,61 @@ pub struct NiGeometryData {
 
 impl Load for NiGeometryData {
     fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
-        let base = stream.load()?;
+        Ok(NiGeometryDataRef::load(stream)?.into_owned())
+    }
+}
+
+impl Save for NiGeometryData {
+    fn save(&self, stream: &mut Writer) -> io::Result<()> {
+        stream.save(&self.base)?;
+        stream.save_as::<u16>(self.vertices.len())?;
+        stream.save_bool(!self.vertices.is_empty())?;
+        stream.save_vec(&self.vertices)?;
+        stream.save_bool(!self.normals.is_empty())?;
+        stream.save_vec(&self.normals)?;
+        stream.save(&self.bound)?;
+        stream.save_bool(!self.vertex_colors.is_empty())?;
+        stream.save_vec(&self.vertex_colors)?;
+        stream.save_as::<u16>(self.num_uv_sets())?;
+        if stream.version <= version::V4_0_0_2 {
+            stream.save_bool(!self.uv_sets.is_empty())?;
+        }
+        stream.save_vec(&self.uv_sets)?;
+        Ok(())
+    }
+}
+
+/// [`NiGeometryData`] with its arrays borrowed from the bytes it's loaded from where they're
+/// aligned, so they can be copied straight into mesh buffers
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NiGeometryDataRef<'a> {
+    pub vertices: Cow<'a, [Vec3]>,
+    pub normals: Cow<'a, [Vec3]>,
+    pub bound: NiBound,
+    pub vertex_colors: Cow<'a, [ColorA]>,
+    pub uv_sets: Cow<'a, [Vec2]>,
+}
+
+impl<'a> NiGeometryDataRef<'a> {
+    pub fn load(stream: &mut Reader<'a>) -> io::Result<Self> {
+        let _base: NiObject = stream.load()?;
         let num_vertices = stream.load_as::<u16, usize>()?;
-        let has_vertices = stream.load::<u32>()? != 0;
+        let has_vertices = stream.load_bool()?;
         let num_vertices = if has_vertices { num_vertices } else { 0 };
-        let vertices = stream.load_vec(num_vertices)?;
-        let has_normals = stream.load::<u32>()? != 0;
+        let vertices = stream.load_slice(num_vertices)?;
+        let has_normals = stream.load_bool()?;
         let num_normals = if has_normals { num_vertices } else { 0 };
-        let normals = stream.load_vec(num_normals)?;
+        let normals = stream.load_slice(num_normals)?;
         let bound = stream.load()?;
-        let has_vertex_colors = stream.load::<u32>()? != 0;
+        let has_vertex_colors = stream.load_bool()?;
         let num_vertex_colors = if has_vertex_colors { num_vertices } else { 0 };
-        let vertex_colors = stream.load_vec(num_vertex_colors)?;
+        let vertex_colors = stream.load_slice(num_vertex_colors)?;
         let num_uv_sets = stream.load_as::<u16, usize>()?;
-        let has_uv_sets = stream.load::<u32>()? != 0;
+        // later versions only go by the count
+        let has_uv_sets = stream.version > version::V4_0_0_2 || stream.load_bool()?;
         let num_uv_sets = if has_uv_sets { num_uv_sets } else { 0 };
-        let uv_sets = stream.load_vec(num_vertices * num_uv_sets)?;
+        let uv_sets = stream.load_slice(num_vertices * num_uv_sets)?;
         Ok(Self {
-            base,
             vertices,
             normals,
             bound,
@@ -38,23 +78,40 @@ impl Load for NiGeometryData {
             uv_sets,
         })
     }
+
+    pub fn into_owned(self) -> NiGeometryData {
+        NiGeometryData {
+            base: NiObject,
+            vertices: self.vertices.into_owned(),
+            normals: self.normals.into_owned(),
+            bound: self.bound,
+            vertex_colors: self.vertex_colors.into_owned(),
+            uv_sets: self.uv_sets.into_owned(),
+        }
+    }
 }
 
-impl Save for NiGeometryData {
-    fn save(&self, stream: &mut Writer) -> io::Result<()> {
-        stream.save(&self.base)?;
-        stream.save_as::<u16>(self.vertices.len())?;
-        stream.save_as::<u32>(!self.vertices.is_empty())?;
-        stream.save_vec(&self.vertices)?;
-        stream.save_as::<u32>(!self.normals.is_empty())?;
-        stream.save_vec(&self.normals)?;
-        stream.save(&self.bound)?;
-        stream.save_as::<u32>(!self.vertex_colors.is_empty())?;
-        stream.save_vec(&self.vertex_colors)?;
-        stream.save_as::<u16>(self.num_uv_sets())?;
-        stream.save_as::<u32>(!self.uv_sets.is_empty())?;
-        stream.save_vec(&self.uv_sets)?;
-        Ok(())
+impl<'a> From<&'a NiGeometryData> for NiGeometryDataRef<'a> {
+    fn from(data: &'a NiGeometryData) -> Self {
+        Self {
+            vertices: Cow::Borrowed(&data.vertices),
+            normals: Cow::Borrowed(&data.normals),
+            bound: data.bound,
+            vertex_colors: Cow::Borrowed(&data.vertex_colors),
+            uv_sets: Cow::Borrowed(&data.uv_sets),
+        }
+    }
+}
+
+impl From<NiGeometryData> for NiGeometryDataRef<'static> {
+    fn from(data: NiGeometryData) -> Self {
+        Self {
+            vertices: Cow::Owned(data.vertices),
+            normals: Cow::Owned(data.normals),
+            bound: data.bound,
+            vertex_colors: Cow::Owned(data.vertex_colors),
+            uv_sets: Cow::Owned(data.uv_sets),
+        }
     }
 }
 
@@ -0,0 +1,364 @@
+// internal imports
+use crate::prelude::*;
+
+/// File versions the layout of the header or of blocks changes at
+pub mod version {
+    pub const V3_1_0_1: u32 = 0x0301_0001;
+    pub const V4_0_0_2: u32 = 0x0400_0002;
+    pub const V4_1_0_12: u32 = 0x0401_000C;
+    pub const V4_2_0_2: u32 = 0x0402_0002;
+    pub const V4_2_1_0: u32 = 0x0402_0100;
+    pub const V4_2_2_0: u32 = 0x0402_0200;
+    pub const V5_0_0_1: u32 = 0x0500_0001;
+    pub const V5_0_0_6: u32 = 0x0500_0006;
+    pub const V10_0_1_2: u32 = 0x0A00_0102;
+    pub const V10_0_1_8: u32 = 0x0A00_0108;
+    pub const V10_1_0_0: u32 = 0x0A01_0000;
+    pub const V20_0_0_3: u32 = 0x1400_0003;
+    pub const V20_1_0_1: u32 = 0x1401_0001;
+    pub const V20_2_0_5: u32 = 0x1402_0005;
+    pub const V20_3_1_2: u32 = 0x1403_0102;
+    pub const V30_0_0_0: u32 = 0x1E00_0000;
+}
+
+/// The start of the header line, the version follows
+const HEADER_PREFIXES: [&[u8]; 2] = [
+    b"NetImmerse File Format, Version ",
+    b"Gamebryo File Format, Version ",
+];
+
+/// Everything in front of the blocks. Older files only have the header line, version and block
+/// count, later ones add a user version, a table of block types and sizes, and shared strings.
+#[derive(Clone, Debug, PartialEq, Eq, SmartDefault)]
+pub struct NiHeader {
+    #[default(NiStream::VERSION)]
+    pub version: u32,
+    #[default(true)]
+    pub little_endian: bool,
+    pub user_version: u32,
+    /// The block count the file was read with, saving writes the count of the stream instead
+    pub num_blocks: u32,
+    pub bs_header: Option<BsStreamHeader>,
+    pub metadata: Vec<u8>,
+    pub block_types: Vec<BString>,
+    pub block_type_hashes: Vec<u32>,
+    pub block_type_indices: Vec<u16>,
+    pub block_sizes: Vec<u32>,
+    pub max_string_length: u32,
+    pub strings: Vec<BString>,
+    pub groups: Vec<u32>,
+}
+
+/// The extra header Bethesda games write after the block count
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BsStreamHeader {
+    pub bs_version: u32,
+    pub author: BString,
+    pub unknown_int: u32,
+    pub process_script: BString,
+    pub export_script: BString,
+    pub max_filepath: BString,
+}
+
+impl NiHeader {
+    /// Whether blocks of this version can be loaded and saved
+    pub const fn is_supported_version(version: u32) -> bool {
+        matches!(
+            version,
+            version::V4_0_0_2
+                | version::V4_1_0_12
+                | version::V4_2_0_2
+                | version::V4_2_1_0
+                | version::V4_2_2_0
+        )
+    }
+
+    /// The first line of the file, without the line break
+    pub fn header_string(&self) -> String {
+        let name = if self.version < version::V10_1_0_0 {
+            "NetImmerse"
+        } else {
+            "Gamebryo"
+        };
+        format!(
+            "{name} File Format, Version {}",
+            version_string(self.version)
+        )
+    }
+
+    const fn has_bs_header(&self) -> bool {
+        self.version == version::V10_0_1_2
+            || (self.version >= version::V10_0_1_8 && self.user_version >= 3)
+    }
+}
+
+impl Load for NiHeader {
+    fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
+        let line = load_line(stream)?;
+        let Some(line_version) = HEADER_PREFIXES
+            .iter()
+            .find_map(|prefix| line.strip_prefix(*prefix))
+            .and_then(parse_version)
+        else {
+            return Reader::error("Invalid NIF Header");
+        };
+
+        // files before 3.1 only have the version in the header line, followed by a copyright
+        let version = if line_version < version::V3_1_0_1 {
+            for _ in 0..3 {
+                load_line(stream)?;
+            }
+            line_version
+        } else {
+            stream.load()?
+        };
+        let little_endian = version < version::V20_0_0_3 || stream.load::<u8>()? != 0;
+        if !little_endian {
+            return Reader::error("Big endian NIFs aren't supported");
+        }
+        let user_version = if version >= version::V10_0_1_8 {
+            stream.load()?
+        } else {
+            0
+        };
+        let num_blocks = if version >= version::V3_1_0_1 {
+            stream.load()?
+        } else {
+            0
+        };
+        stream.check_block_count(num_blocks as usize)?;
+
+        let mut header = Self {
+            version,
+            little_endian,
+            user_version,
+            num_blocks,
+            ..default()
+        };
+        if header.has_bs_header() {
+            header.bs_header = Some(stream.load()?);
+        }
+        if version >= version::V30_0_0_0 {
+            let len = stream.load_as::<u32, usize>()?;
+            stream.check_array_len(len)?;
+            header.metadata = stream.load_bytes(len)?;
+        }
+        if version >= version::V5_0_0_1 {
+            let num_block_types: u16 = stream.load()?;
+            if version >= version::V20_3_1_2 {
+                header.block_type_hashes = stream.load_vec(num_block_types)?;
+            } else {
+                header.block_types = stream.load_seq(num_block_types)?;
+            }
+            header.block_type_indices = stream.load_vec(num_blocks)?;
+        }
+        if version >= version::V20_2_0_5 {
+            header.block_sizes = stream.load_vec(num_blocks)?;
+        }
+        if version >= version::V20_1_0_1 {
+            let num_strings: u32 = stream.load()?;
+            header.max_string_length = stream.load()?;
+            header.strings = stream.load_seq(num_strings)?;
+        }
+        if version >= version::V5_0_0_6 {
+            header.groups = stream.load()?;
+        }
+        Ok(header)
+    }
+}
+
+impl Save for NiHeader {
+    fn save(&self, stream: &mut Writer) -> io::Result<()> {
+        let version = self.version;
+        if version < version::V3_1_0_1 {
+            return Writer::error("NIFs before 3.1 can't be saved");
+        }
+        if !self.little_endian {
+            return Writer::error("Big endian NIFs aren't supported");
+        }
+        stream.save_bytes(self.header_string().as_bytes())?;
+        stream.save(&b'\n')?;
+        stream.save(&version)?;
+        if version >= version::V20_0_0_3 {
+            stream.save(&1u8)?;
+        }
+        if version >= version::V10_0_1_8 {
+            stream.save(&self.user_version)?;
+        }
+        stream.save(&self.num_blocks)?;
+        if self.has_bs_header() {
+            stream.save(&self.bs_header.clone().unwrap_or_default())?;
+        }
+        if version >= version::V30_0_0_0 {
+            stream.save_as::<u32>(self.metadata.len())?;
+            stream.save_bytes(&self.metadata)?;
+        }
+        if version >= version::V5_0_0_1 {
+            if version >= version::V20_3_1_2 {
+                stream.save_as::<u16>(self.block_type_hashes.len())?;
+                stream.save_vec(&self.block_type_hashes)?;
+            } else {
+                stream.save_as::<u16>(self.block_types.len())?;
+                stream.save_seq(&self.block_types)?;
+            }
+            stream.save_vec(&self.block_type_indices)?;
+        }
+        if version >= version::V20_2_0_5 {
+            stream.save_vec(&self.block_sizes)?;
+        }
+        if version >= version::V20_1_0_1 {
+            stream.save_as::<u32>(self.strings.len())?;
+            stream.save(&self.max_string_length)?;
+            stream.save_seq(&self.strings)?;
+        }
+        if version >= version::V5_0_0_6 {
+            stream.save(&self.groups)?;
+        }
+        Ok(())
+    }
+}
+
+impl Load for BsStreamHeader {
+    fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
+        let bs_version: u32 = stream.load()?;
+        let author = load_export_string(stream)?;
+        let unknown_int = if bs_version > 130 { stream.load()? } else { 0 };
+        let process_script = if bs_version < 131 {
+            load_export_string(stream)?
+        } else {
+            default()
+        };
+        let export_script = load_export_string(stream)?;
+        let max_filepath = if bs_version >= 103 {
+            load_export_string(stream)?
+        } else {
+            default()
+        };
+        Ok(Self {
+            bs_version,
+            author,
+            unknown_int,
+            process_script,
+            export_script,
+            max_filepath,
+        })
+    }
+}
+
+impl Save for BsStreamHeader {
+    fn save(&self, stream: &mut Writer) -> io::Result<()> {
+        stream.save(&self.bs_version)?;
+        save_export_string(stream, &self.author)?;
+        if self.bs_version > 130 {
+            stream.save(&self.unknown_int)?;
+        }
+        if self.bs_version < 131 {
+            save_export_string(stream, &self.process_script)?;
+        }
+        save_export_string(stream, &self.export_script)?;
+        if self.bs_version >= 103 {
+            save_export_string(stream, &self.max_filepath)?;
+        }
+        Ok(())
+    }
+}
+
+/// Loading of values whose layout depends on the version being read
+pub trait NiReader {
+    /// A bool, 32 bits wide up to 4.0.0.2 and a single byte after
+    fn load_bool(&mut self) -> io::Result<bool>;
+}
+
+impl NiReader for Reader<'_> {
+    fn load_bool(&mut self) -> io::Result<bool> {
+        if self.version > version::V4_0_0_2 {
+            Ok(self.load::<u8>()? != 0)
+        } else {
+            Ok(self.load::<u32>()? != 0)
+        }
+    }
+}
+
+/// Saving of values whose layout depends on the version being written
+pub trait NiWriter {
+    /// A bool, 32 bits wide up to 4.0.0.2 and a single byte after
+    fn save_bool(&mut self, value: bool) -> io::Result<()>;
+}
+
+impl NiWriter for Writer {
+    fn save_bool(&mut self, value: bool) -> io::Result<()> {
+        if self.version > version::V4_0_0_2 {
+            self.save(&u8::from(value))
+        } else {
+            self.save(&u32::from(value))
+        }
+    }
+}
+
+/// Format a version the way header lines spell it, like `4.0.0.2`
+fn version_string(version: u32) -> String {
+    let [a, b, c, d] = version.to_be_bytes();
+    format!("{a}.{b}.{c}.{d}")
+}
+
+/// Parse a version spelled like `4.0.0.2`, missing parts are zero
+fn parse_version(text: &[u8]) -> Option<u32> {
+    let mut parts = text.to_str().ok()?.split('.');
+    let mut version = 0;
+    for shift in [24, 16, 8, 0] {
+        let part = match parts.next() {
+            Some(part) => part.trim().parse::<u8>().ok()?,
+            None => 0,
+        };
+        version |= u32::from(part) << shift;
+    }
+    parts.next().is_none().then_some(version)
+}
+
+/// Load a line of text ending in a line break, without the line break
+fn load_line(stream: &mut Reader<'_>) -> io::Result<BString> {
+    let mut line = BString::default();
+    loop {
+        match stream.load::<u8>()? {
+            b'\n' => return Ok(line),
+            _ if line.len() >= 256 => return Reader::error("Invalid NIF Header"),
+            byte => line.push(byte),
+        }
+    }
+}
+
+/// Load a string with a single byte length, the null terminator included
+fn load_export_string(stream: &mut Reader<'_>) -> io::Result<BString> {
+    let len: u8 = stream.load()?;
+    Ok(stream.load_bytes(len.into())?.into())
+}
+
+fn save_export_string(stream: &mut Writer, value: &BString) -> io::Result<()> {
+    stream.save_as::<u8>(value.len())?;
+    stream.save_bytes(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_4_2_2_0() {
+        let mut stream = test_shape_stream();
+        stream.header.version = version::V4_2_2_0;
+        let data = stream
+            .objects_of_type_mut::<NiTriShapeData>()
+            .next()
+            .unwrap();
+        data.uv_sets = vec![Vec2::ZERO, Vec2::X, Vec2::Y];
+        let bytes = stream.save_bytes().unwrap();
+        assert!(bytes.starts_with(b"NetImmerse File Format, Version 4.2.2.0\n"));
+
+        let loaded = NiStream::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.header.version, version::V4_2_2_0);
+        assert_eq!(loaded.header.num_blocks, 2);
+        let data = loaded.objects_of_type::<NiTriShapeData>().next().unwrap();
+        assert_eq!(data.num_uv_sets(), 1);
+        assert_eq!(data.uv_sets, [Vec2::ZERO, Vec2::X, Vec2::Y]);
+    }
+}
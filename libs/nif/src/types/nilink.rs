@@ -61,16 +61,21 @@ where
     }
 }
 
+/// The block index of `NiType::Empty` placeholders when saving, links to them are written as null
+pub(super) const LOST_BLOCK_INDEX: u64 = u64::MAX;
+
 impl<T> Save for NiLink<T>
 where
     T: Save,
 {
     fn save(&self, stream: &mut Writer) -> io::Result<()> {
         if self.is_null() {
-            stream.save(&-1i32)?;
-        } else {
-            let key = self.key.data().as_ffi();
-            stream.save_as::<i32>(stream.context[&key])?;
+            return stream.save(&-1i32);
+        }
+        match stream.context.get(&self.key.data().as_ffi()) {
+            Some(&LOST_BLOCK_INDEX) => stream.save(&-1i32)?,
+            Some(&index) => stream.save_as::<i32>(index)?,
+            None => Writer::error(format!("Link to {:?}, which isn't saved", self.key))?,
         }
         Ok(())
     }
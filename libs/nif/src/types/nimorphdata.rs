@@ -18,6 +18,7 @@ impl Load for NiMorphData {
         let num_targets: u32 = stream.load()?;
         let num_vertices: u32 = stream.load()?;
         let relative_targets = stream.load::<u8>()? != 0;
+        stream.check_alloc::<MorphTarget>(num_targets as usize)?;
         let targets = (0..num_targets).load(|_| MorphTarget::load(stream, num_vertices))?;
         Ok(Self {
             base,
@@ -53,9 +54,9 @@ impl MorphTarget {
             KeyType::BezKey => NiFloatKey::BezKey(stream.load_vec(num_keys)?),
             KeyType::TCBKey => NiFloatKey::TCBKey(stream.load_vec(num_keys)?),
             _ if (num_keys == 0) => default(), // Allowed only when there are no keys.
-            _ => Reader::error(format!("Invalid KeyType: {key_type:?}"))?,
+            _ => Reader::invalid_enum_value(format!("Invalid KeyType: {key_type:?}"))?,
         };
-        let vertices = stream.load_vec(num_vertices)?;
+        let vertices = stream.load_bulk_vec(num_vertices)?;
         Ok(Self { keys, vertices })
     }
 }
@@ -16,7 +16,7 @@ impl Load for NiParticlesData {
         let num_particles: u16 = stream.load()?;
         let particle_radius = stream.load()?;
         let num_active: u16 = stream.load()?;
-        let has_sizes = stream.load::<u32>()? != 0;
+        let has_sizes = stream.load_bool()?;
         let num_sizes = if has_sizes { base.vertices.len() } else { 0 };
         let sizes = stream.load_vec(num_sizes)?;
         Ok(Self {
@@ -35,7 +35,7 @@ impl Save for NiParticlesData {
         stream.save(&self.num_particles)?;
         stream.save(&self.particle_radius)?;
         stream.save(&self.num_active)?;
-        stream.save_as::<u32>(!self.sizes.is_empty())?;
+        stream.save_bool(!self.sizes.is_empty())?;
         stream.save_vec(&self.sizes)?;
         Ok(())
     }
@@ -20,7 +20,7 @@ impl Load for NiPixelData {
         let pixel_stride = stream.load()?;
         let mipmaps = stream.load_vec(num_mipmap_levels)?;
         let num_pixel_data: u32 = stream.load()?;
-        let pixel_data = stream.load_vec(num_pixel_data)?;
+        let pixel_data = stream.load_bulk_vec(num_pixel_data)?;
         Ok(Self {
             base,
             pixel_format,
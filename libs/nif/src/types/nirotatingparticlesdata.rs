@@ -10,7 +10,7 @@ pub struct NiRotatingParticlesData {
 impl Load for NiRotatingParticlesData {
     fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
         let base: NiParticlesData = stream.load()?;
-        let has_rotations = stream.load::<u32>()? != 0;
+        let has_rotations = stream.load_bool()?;
         let num_rotations = if has_rotations { base.vertices.len() } else { 0 };
         let rotations = stream.load_seq(num_rotations)?;
         Ok(Self { base, rotations })
@@ -20,7 +20,7 @@ impl Load for NiRotatingParticlesData {
 impl Save for NiRotatingParticlesData {
     fn save(&self, stream: &mut Writer) -> io::Result<()> {
         stream.save(&self.base)?;
-        stream.save_as::<u32>(!self.rotations.is_empty())?;
+        stream.save_bool(!self.rotations.is_empty())?;
         stream.save_seq(&self.rotations)?;
         Ok(())
     }
@@ -15,10 +15,10 @@ impl Load for NiScreenPolygon {
         let base = stream.load()?;
         let num_vertices: u16 = stream.load()?;
         let vertices = stream.load_vec(num_vertices)?;
-        let has_uv_coords = stream.load::<u32>()? != 0;
+        let has_uv_coords = stream.load_bool()?;
         let num_uv_coords = if has_uv_coords { num_vertices } else { 0 };
         let uv_coords = stream.load_vec(num_uv_coords)?;
-        let has_vertex_colors = stream.load::<u32>()? != 0;
+        let has_vertex_colors = stream.load_bool()?;
         let num_vertex_colors = if has_vertex_colors { num_vertices } else { 0 };
         let vertex_colors = stream.load_vec(num_vertex_colors)?;
         let property_states = stream.load()?;
@@ -37,9 +37,9 @@ impl Save for NiScreenPolygon {
         stream.save(&self.base)?;
         stream.save_as::<u16>(self.vertices.len())?;
         stream.save_vec(&self.vertices)?;
-        stream.save_as::<u32>(!self.uv_coords.is_empty())?;
+        stream.save_bool(!self.uv_coords.is_empty())?;
         stream.save_vec(&self.uv_coords)?;
-        stream.save_as::<u32>(!self.vertex_colors.is_empty())?;
+        stream.save_bool(!self.vertex_colors.is_empty())?;
         stream.save_vec(&self.vertex_colors)?;
         stream.save(&self.property_states)?;
         Ok(())
@@ -19,3 +19,9 @@ impl Save for NiShadeProperty {
         Ok(())
     }
 }
+
+impl NiShadeProperty {
+    flag_props! {
+        smooth @ (mask = 0x0001) -> bool,
+    }
+}
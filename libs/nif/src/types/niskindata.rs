@@ -10,6 +10,8 @@ pub struct NiSkinData {
     #[default(1.0)]
     pub scale: f32,
     pub skin_partition: NiLink<NiSkinPartition>,
+    #[default(true)]
+    pub has_vertex_weights: bool, // since 4.2.1.0
     pub bone_data: Vec<BoneData>,
 }
 
@@ -21,13 +23,16 @@ impl Load for NiSkinData {
         let scale = stream.load()?;
         let num_bone_data: u32 = stream.load()?;
         let skin_partition = stream.load()?;
-        let bone_data = stream.load_seq(num_bone_data)?;
+        let has_vertex_weights = stream.version < version::V4_2_1_0 || stream.load::<u8>()? != 0;
+        stream.check_alloc::<BoneData>(num_bone_data as usize)?;
+        let bone_data = (0..num_bone_data).load(|_| BoneData::load(stream, has_vertex_weights))?;
         Ok(Self {
             base,
             rotation,
             translation,
             scale,
             skin_partition,
+            has_vertex_weights,
             bone_data,
         })
     }
@@ -41,7 +46,13 @@ impl Save for NiSkinData {
         stream.save(&self.scale)?;
         stream.save_as::<u32>(self.bone_data.len())?;
         stream.save(&self.skin_partition)?;
-        stream.save_seq(&self.bone_data)?;
+        if stream.version >= version::V4_2_1_0 {
+            stream.save_as::<u8>(self.has_vertex_weights)?;
+        }
+        let has_vertex_weights = self.has_vertex_weights || stream.version < version::V4_2_1_0;
+        for bone_data in &self.bone_data {
+            bone_data.save(stream, has_vertex_weights)?;
+        }
         Ok(())
     }
 }
@@ -55,34 +66,70 @@ pub struct BoneData {
     pub scale: f32,
     pub bound: NiBound,
     pub vertex_weights: Vec<(u16, f32)>,
+    /// Number of vertex weights the bone has in the skin partition, written in place of the
+    /// weights when `NiSkinData::has_vertex_weights` is false
+    pub num_partition_weights: u16,
 }
 
-impl Load for BoneData {
-    fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
+impl BoneData {
+    /// Files that keep vertex weights in a skin partition leave them out, only their count is
+    /// written, into `num_partition_weights`
+    fn load(stream: &mut Reader<'_>, has_vertex_weights: bool) -> io::Result<Self> {
         let rotation = stream.load()?;
         let translation = stream.load()?;
         let scale = stream.load()?;
         let bound = stream.load()?;
         let num_vertex_weights: u16 = stream.load()?;
-        let vertex_weights = stream.load_seq(num_vertex_weights)?;
+        let (vertex_weights, num_partition_weights) = if has_vertex_weights {
+            (stream.load_seq(num_vertex_weights)?, 0)
+        } else {
+            (Vec::new(), num_vertex_weights)
+        };
         Ok(Self {
             rotation,
             translation,
             scale,
             bound,
             vertex_weights,
+            num_partition_weights,
         })
     }
-}
 
-impl Save for BoneData {
-    fn save(&self, stream: &mut Writer) -> io::Result<()> {
+    fn save(&self, stream: &mut Writer, has_vertex_weights: bool) -> io::Result<()> {
         stream.save(&self.rotation)?;
         stream.save(&self.translation)?;
         stream.save(&self.scale)?;
         stream.save(&self.bound)?;
-        stream.save_as::<u16>(self.vertex_weights.len())?;
-        stream.save_seq(&self.vertex_weights)?;
+        if has_vertex_weights {
+            stream.save_as::<u16>(self.vertex_weights.len())?;
+            stream.save_seq(&self.vertex_weights)?;
+        } else {
+            stream.save(&self.num_partition_weights)?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_weight_count_round_trip() {
+        let skin_data = NiSkinData {
+            has_vertex_weights: false,
+            bone_data: vec![BoneData {
+                num_partition_weights: 3,
+                ..default()
+            }],
+            ..default()
+        };
+        let mut writer = Writer::new(Vec::new());
+        writer.version = version::V4_2_1_0;
+        writer.save(&skin_data).unwrap();
+        let bytes = writer.cursor.into_inner();
+        let mut reader = Reader::new(&bytes);
+        reader.version = version::V4_2_1_0;
+        assert_eq!(reader.load::<NiSkinData>().unwrap(), skin_data);
+    }
+}
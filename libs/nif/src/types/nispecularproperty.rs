@@ -19,3 +19,9 @@ impl Save for NiSpecularProperty {
         Ok(())
     }
 }
+
+impl NiSpecularProperty {
+    flag_props! {
+        specular @ (mask = 0x0001) -> bool,
+    }
+}
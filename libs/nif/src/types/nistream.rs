@@ -7,14 +7,22 @@ use std::path::Path;
 use slotmap::{DenseSlotMap, Key, new_key_type};
 
 // internal imports
+use super::nilink::LOST_BLOCK_INDEX;
 use crate::prelude::*;
 
 new_key_type! { pub struct NiKey; }
 
 #[derive(Clone, Debug, Default)]
 pub struct NiStream {
+    pub header: NiHeader,
     pub objects: DenseSlotMap<NiKey, NiType>,
     pub roots: Vec<NiLink<NiObject>>,
+    /// Where the blocks are in the file, for streams loaded by [`Self::load_bytes_indexed`]
+    pub index: Option<NiBlockIndex>,
+    /// What loading a file may allocate, lower them for files from unknown sources
+    pub limits: ReaderLimits,
+    /// The code page strings are loaded and saved with
+    pub encoding: NifEncoding,
 }
 
 impl NiStream {
@@ -25,60 +33,118 @@ impl NiStream {
         default()
     }
 
-    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, NifError> {
         let mut stream = Self::new();
         stream.load_path(path)?;
         Ok(stream)
     }
 
-    pub fn from_path_offset(path: impl AsRef<Path>, offset: u64, size: usize) -> io::Result<Self> {
+    pub fn from_path_offset(
+        path: impl AsRef<Path>,
+        offset: u64,
+        size: usize,
+    ) -> Result<Self, NifError> {
         let mut file = std::fs::File::open(path)?;
         file.seek(io::SeekFrom::Start(offset))?;
-
-        let mut bytes = vec![0; size];
-        file.read_exact(&mut bytes)?;
-
         let mut stream = Self::new();
-        stream.load_bytes(&bytes)?;
+        stream.load_read_seek_within(file, size as u64)?;
         Ok(stream)
     }
 
-    pub fn load_path(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
-        self.load_bytes(&std::fs::read(path)?)
+    pub fn load_path(&mut self, path: impl AsRef<Path>) -> Result<(), NifError> {
+        self.load_read_seek(std::fs::File::open(path)?)
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NifError> {
         let mut stream = Self::new();
         stream.load_bytes(bytes)?;
         Ok(stream)
     }
 
-    pub fn load_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+    pub fn load_bytes(&mut self, bytes: &[u8]) -> Result<(), NifError> {
         let mut stream = Reader::new(bytes);
+        stream.encoding = self.encoding.resolve(bytes, self.limits);
+        self.load_stream(&mut stream)
+    }
 
-        // validate header
-        let header: [u8; 40] = stream.load()?;
-        if header != Self::HEADER {
-            return Reader::error("Invalid NIF Header");
+    /// Load a NIF straight from a reader, like an archive entry or a decompressor, without
+    /// reading it into memory first. Reading is buffered. Guessing the encoding needs the whole
+    /// file, so with [`NifEncoding::Detect`] it's read into memory after all.
+    pub fn from_reader(reader: impl Read) -> Result<Self, NifError> {
+        let mut stream = Self::new();
+        stream.load_reader(reader)?;
+        Ok(stream)
+    }
+
+    pub fn load_reader(&mut self, mut reader: impl Read) -> Result<(), NifError> {
+        if self.encoding == NifEncoding::Detect {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes)?;
+            return self.load_bytes(&bytes);
         }
+        let mut stream = Reader::from_read(reader);
+        stream.encoding = self.encoding.encoding();
+        self.load_stream(&mut stream)
+    }
+
+    /// Load a NIF from a seekable stream, like a file, starting at its current position. Like
+    /// [`Self::from_reader`], but data that isn't kept is seeked over rather than read.
+    pub fn from_read_seek(reader: impl Read + Seek) -> Result<Self, NifError> {
+        let mut stream = Self::new();
+        stream.load_read_seek(reader)?;
+        Ok(stream)
+    }
+
+    pub fn load_read_seek(&mut self, reader: impl Read + Seek) -> Result<(), NifError> {
+        self.load_read_seek_within(reader, u64::MAX)
+    }
 
-        // validate version
-        let version: u32 = stream.load()?;
-        if version != Self::VERSION {
-            return Reader::error("Invalid NIF Version");
+    /// Load a NIF from a seekable stream, failing if it's longer than `size` bytes
+    fn load_read_seek_within(
+        &mut self,
+        reader: impl Read + Seek,
+        size: u64,
+    ) -> Result<(), NifError> {
+        if self.encoding == NifEncoding::Detect {
+            let mut bytes = Vec::new();
+            reader.take(size).read_to_end(&mut bytes)?;
+            return self.load_bytes(&bytes);
         }
+        let mut stream = Reader::from_read_seek(reader)?;
+        stream.encoding = self.encoding.encoding();
+        self.load_stream(&mut stream)?;
+        if stream.position() > size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(())
+    }
+
+    fn load_stream(&mut self, stream: &mut Reader<'_>) -> Result<(), NifError> {
+        stream.limits = self.limits;
+
+        // validate header and version
+        self.header = load_header(stream)?;
 
         // allocate objects
-        let num_objects = stream.load_as::<u32, usize>()?;
+        let num_objects = self.header.num_blocks as usize;
         self.objects.reserve(num_objects);
 
         // populate objects
-        for _ in 0..num_objects {
-            self.objects.insert(stream.load()?);
+        for block_index in 0..num_objects {
+            self.objects
+                .insert(load_block(stream, block_index, num_objects)?);
         }
 
+        self.load_roots(stream)
+    }
+
+    fn load_roots(&mut self, stream: &mut Reader<'_>) -> Result<(), NifError> {
         // allocate roots
+        let offset = stream.position();
         let num_roots = stream.load_as::<u32, usize>()?;
+        stream
+            .check_alloc::<NiLink<NiObject>>(num_roots)
+            .map_err(|error| limit_error(error, "root list", offset))?;
         self.roots.reserve(num_roots);
 
         // populate roots
@@ -89,6 +155,79 @@ impl NiStream {
         Ok(())
     }
 
+    /// Load a NIF like [`Self::load_bytes`], and decode its `NiTriShapeData` blocks a second time
+    /// with their vertex and triangle arrays borrowed from `bytes`. Meshes built from those copy
+    /// the arrays once, straight out of the file, while the stream keeps its own.
+    pub fn load_bytes_with_shape_data<'a>(
+        &mut self,
+        bytes: &'a [u8],
+    ) -> Result<Vec<(NiKey, NiTriShapeDataRef<'a>)>, NifError> {
+        let mut stream = Reader::new(bytes);
+        stream.limits = self.limits;
+        stream.encoding = self.encoding.resolve(bytes, self.limits);
+
+        // validate header and version
+        self.header = load_header(&mut stream)?;
+
+        // allocate objects
+        let num_objects = self.header.num_blocks as usize;
+        self.objects.reserve(num_objects);
+
+        // populate objects, keeping where the shape data is
+        let mut shape_data = Vec::new();
+        for block_index in 0..num_objects {
+            let offset = stream.position();
+            let ni_type = load_block(&mut stream, block_index, num_objects)?;
+            let is_shape_data = matches!(ni_type, NiType::NiTriShapeData(_));
+            let key = self.objects.insert(ni_type);
+            if is_shape_data {
+                let data = NiTriShapeDataRef::load_block(bytes, self, block_index, offset)?;
+                shape_data.push((key, data));
+            }
+        }
+
+        self.load_roots(&mut stream)?;
+
+        Ok(shape_data)
+    }
+
+    /// Load a NIF like [`Self::from_bytes`], but recover from blocks that fail to load instead
+    /// of giving up. Lost blocks are left as `NiType::Empty` and described in the diagnostics.
+    pub fn from_bytes_lenient(bytes: &[u8]) -> Result<(Self, Vec<NifDiagnostic>), NifError> {
+        let mut stream = Self::new();
+        let diagnostics = stream.load_bytes_lenient(bytes)?;
+        Ok((stream, diagnostics))
+    }
+
+    pub fn load_bytes_lenient(&mut self, bytes: &[u8]) -> Result<Vec<NifDiagnostic>, NifError> {
+        let mut stream = Reader::new(bytes);
+        stream.limits = self.limits;
+        stream.encoding = self.encoding.resolve(bytes, self.limits);
+        let mut diagnostics = Vec::new();
+
+        // validate header and version
+        self.header = load_header(&mut stream)?;
+
+        // allocate objects
+        let num_objects = self.header.num_blocks as usize;
+        self.objects.reserve(num_objects);
+
+        // populate objects
+        for block_index in 0..num_objects {
+            self.objects.insert(load_block_lenient(
+                &mut stream,
+                block_index,
+                num_objects,
+                &mut diagnostics,
+            ));
+        }
+
+        // populate roots
+        self.roots = load_roots_lenient(&mut stream, num_objects, &mut diagnostics);
+
+        Ok(diagnostics)
+    }
+
     pub fn save_path(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
         let mut file = std::fs::File::create(path)?;
         file.write_all(self.save_bytes()?.as_slice())?;
@@ -97,18 +236,27 @@ impl NiStream {
 
     pub fn save_bytes(&mut self) -> io::Result<Vec<u8>> {
         let mut stream = Writer::new(vec![]);
-
-        // write header
-        stream.save(&Self::HEADER)?;
-
-        // write version
-        stream.save(&Self::VERSION)?;
+        if self.index.is_some() {
+            Writer::error("Indexed streams can't be saved, their blocks aren't loaded")?;
+        }
+        if !NiHeader::is_supported_version(self.header.version) {
+            Writer::error(format!(
+                "Unsupported NIF Version {:#x}",
+                self.header.version
+            ))?;
+        }
+        stream.version = self.header.version;
+        stream.user_version = self.header.user_version;
+        stream.encoding = self.encoding.encoding();
 
         // parse objects
         let objects: Vec<_> = self.objects().collect();
 
-        // objects count
-        stream.save_as::<u32>(objects.len())?;
+        // write header, with the count of the objects written
+        stream.save(&NiHeader {
+            num_blocks: u32::try_from(objects.len()).unwrap_or(u32::MAX),
+            ..self.header.clone()
+        })?;
 
         // resolve links
         for (key, _) in &objects {
@@ -116,6 +264,11 @@ impl NiStream {
                 .context
                 .insert(key.data().as_ffi(), stream.context.len() as u64);
         }
+        for (key, object) in &self.objects {
+            if matches!(object, NiType::Empty) {
+                stream.context.insert(key.data().as_ffi(), LOST_BLOCK_INDEX);
+            }
+        }
 
         // write objects
         for (_, object) in objects {
@@ -136,7 +289,10 @@ impl NiStream {
         std::iter::from_fn(move || {
             while let Some(key) = keys.pop() {
                 if !key.is_null() && seen.insert(key) {
-                    if let Some(object) = self.objects.get(key) {
+                    // placeholders of blocks that failed to load aren't written
+                    if let Some(object) = self.objects.get(key)
+                        && !matches!(object, NiType::Empty)
+                    {
                         object.visitor(&mut |key| keys.push(key));
                         return Some((key, object));
                     }
@@ -450,7 +606,9 @@ impl NiStream {
     }
 
     pub fn append(&mut self, other: &mut NiStream) -> Vec<NiLink<NiObject>> {
-        let NiStream { objects, mut roots } = std::mem::take(other);
+        let NiStream {
+            objects, mut roots, ..
+        } = std::mem::take(other);
 
         let remap: HashMap<_, _> = objects
             .into_iter()
@@ -466,11 +624,93 @@ impl NiStream {
     }
 }
 
+/// A stream of an `NiTriShape` and its `NiTriShapeData` of one triangle, rooted at the shape
+#[cfg(test)]
+pub fn test_shape_stream() -> NiStream {
+    let mut stream = NiStream::new();
+    let data = stream.insert(NiTriShapeData {
+        base: NiTriBasedGeomData {
+            base: NiGeometryData {
+                vertices: vec![Vec3::X, Vec3::Y, Vec3::Z],
+                ..default()
+            },
+        },
+        triangles: vec![[0, 1, 2]],
+        ..default()
+    });
+    let shape = stream.insert(NiTriShape {
+        base: NiTriBasedGeom {
+            base: NiGeometry {
+                geometry_data: data.cast(),
+                ..default()
+            },
+        },
+    });
+    stream.roots.push(shape.cast());
+    stream
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use bevy_math::vec3;
 
+    #[test]
+    fn test_load_reader() {
+        let bytes = test_shape_stream().save_bytes().unwrap();
+
+        // a slice only reads, it doesn't seek
+        let streamed = NiStream::from_reader(&bytes[..]).unwrap();
+        assert_eq!(streamed.objects.len(), 2);
+        assert_eq!(streamed.roots.len(), 1);
+        let data = streamed.objects_of_type::<NiTriShapeData>().next().unwrap();
+        assert_eq!(data.vertices, [Vec3::X, Vec3::Y, Vec3::Z]);
+        assert_eq!(data.triangles, [[0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_load_read_seek() {
+        let bytes = test_shape_stream().save_bytes().unwrap();
+
+        // like an entry in the middle of an archive
+        let mut archive = vec![0xFF; 16];
+        archive.extend_from_slice(&bytes);
+        archive.extend_from_slice(&[0xFF; 16]);
+        let mut reader = io::Cursor::new(archive);
+        reader.set_position(16);
+
+        let loaded = NiStream::from_read_seek(reader).unwrap();
+        assert_eq!(loaded.roots.len(), 1);
+        let data = loaded.objects_of_type::<NiTriShapeData>().next().unwrap();
+        assert_eq!(data.vertices, [Vec3::X, Vec3::Y, Vec3::Z]);
+    }
+
+    #[test]
+    fn test_limits() {
+        let bytes = test_shape_stream().save_bytes().unwrap();
+
+        let mut loaded = NiStream::new();
+        loaded.limits.max_array_len = 2;
+        assert!(matches!(
+            loaded.load_bytes(&bytes),
+            Err(NifError::LimitExceeded {
+                block_index: Some(1),
+                ..
+            })
+        ));
+        loaded.limits = ReaderLimits::UNLIMITED;
+        loaded.limits.max_block_count = 0;
+        assert!(matches!(
+            loaded.load_bytes(&bytes),
+            Err(NifError::LimitExceeded {
+                block_index: None,
+                ..
+            })
+        ));
+        loaded.limits = ReaderLimits::default();
+        assert!(loaded.load_bytes(&bytes).is_ok());
+    }
+
     #[ignore = "visual"]
     #[test]
     fn test_bounding_sphere() {
@@ -15,9 +15,10 @@ impl Load for NiTexturingProperty {
         let base = stream.load()?;
         let apply_mode = stream.load()?;
         let num_texture_maps: u32 = stream.load()?;
+        stream.check_alloc::<Option<TextureMap>>(num_texture_maps as usize)?;
         let texture_maps = (0..num_texture_maps).load(|i| {
             Ok({
-                let has_map = stream.load::<u32>()? != 0;
+                let has_map = stream.load_bool()?;
                 if !has_map {
                     None
                 } else if i == BUMP_INDEX {
@@ -41,7 +42,7 @@ impl Save for NiTexturingProperty {
         stream.save(&self.apply_mode)?;
         stream.save_as::<u32>(self.texture_maps.len())?;
         for slot in &self.texture_maps {
-            stream.save_as::<u32>(slot.is_some())?;
+            stream.save_bool(slot.is_some())?;
             match &slot {
                 None => continue,
                 Some(TextureMap::Map(map)) => stream.save(map)?,
@@ -82,8 +83,11 @@ impl Load for Map {
         let texture_index = stream.load_as::<u32, usize>()?;
         let ps2_l = stream.load()?;
         let ps2_k = stream.load()?;
-        let unknown_flag1 = stream.load()?;
-        let unknown_flag2 = stream.load()?;
+        let (unknown_flag1, unknown_flag2) = if stream.version <= version::V4_1_0_12 {
+            (stream.load()?, stream.load()?)
+        } else {
+            (0, 0)
+        };
         Ok(Self {
             base,
             texture,
@@ -107,8 +111,10 @@ impl Save for Map {
         stream.save_as::<u32>(self.texture_index)?;
         stream.save(&self.ps2_l)?;
         stream.save(&self.ps2_k)?;
-        stream.save(&self.unknown_flag1)?;
-        stream.save(&self.unknown_flag2)?;
+        if stream.version <= version::V4_1_0_12 {
+            stream.save(&self.unknown_flag1)?;
+            stream.save(&self.unknown_flag2)?;
+        }
         Ok(())
     }
 }
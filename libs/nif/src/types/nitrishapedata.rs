@@ -1,3 +1,6 @@
+// rust std imports
+use std::borrow::Cow;
+
 // internal imports
 use crate::prelude::*;
 
@@ -10,10 +13,40 @@ pub struct NiTriShapeData {
 
 impl Load for NiTriShapeData {
     fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
-        let base = stream.load()?;
+        Ok(NiTriShapeDataRef::load(stream)?.into_owned())
+    }
+}
+
+impl Save for NiTriShapeData {
+    fn save(&self, stream: &mut Writer) -> io::Result<()> {
+        stream.save(&self.base)?;
+        stream.save_as::<u16>(self.triangles.len())?;
+        stream.save_as::<u32>(self.triangles.len() * 3)?;
+        stream.save_vec(&self.triangles)?;
+        stream.save_as::<u16>(self.shared_normals.len())?;
+        for indices in &self.shared_normals {
+            stream.save_as::<u16>(indices.len())?;
+            stream.save_vec(indices)?;
+        }
+        Ok(())
+    }
+}
+
+/// [`NiTriShapeData`] with its vertex and triangle arrays borrowed from the bytes it's loaded
+/// from, see [`NiStream::load_tri_shape_data_ref`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NiTriShapeDataRef<'a> {
+    pub base: NiGeometryDataRef<'a>,
+    pub triangles: Cow<'a, [[u16; 3]]>,
+    pub shared_normals: Vec<Vec<u16>>,
+}
+
+impl<'a> NiTriShapeDataRef<'a> {
+    pub fn load(stream: &mut Reader<'a>) -> io::Result<Self> {
+        let base = NiGeometryDataRef::load(stream)?;
         let _num_triangles: u16 = stream.load()?;
         let num_triangle_points: u32 = stream.load()?;
-        let triangles = stream.load_vec(num_triangle_points / 3)?;
+        let triangles = stream.load_slice(num_triangle_points / 3)?;
         let num_shared_normals: u16 = stream.load()?;
         let shared_normals = (0..num_shared_normals).load(|_| {
             Ok({
@@ -27,19 +60,54 @@ impl Load for NiTriShapeData {
             shared_normals,
         })
     }
+
+    /// Decode block `block_index` of `bytes`, an `NiTriShapeData` starting at `offset`, with the
+    /// version, limits and encoding of `ni_stream`
+    pub(crate) fn load_block(
+        bytes: &'a [u8],
+        ni_stream: &NiStream,
+        block_index: usize,
+        offset: u64,
+    ) -> Result<Self, NifError> {
+        let mut stream = Reader::new(bytes);
+        stream.version = ni_stream.header.version;
+        stream.user_version = ni_stream.header.user_version;
+        stream.limits = ni_stream.limits;
+        stream.encoding = ni_stream.encoding.encoding();
+        stream
+            .set_position(offset)
+            .and_then(|()| stream.load::<BString>())
+            .and_then(|_| Self::load(&mut stream))
+            .map_err(|error| block_error(&error, block_index, "NiTriShapeData".to_string(), offset))
+    }
+
+    pub fn into_owned(self) -> NiTriShapeData {
+        NiTriShapeData {
+            base: NiTriBasedGeomData {
+                base: self.base.into_owned(),
+            },
+            triangles: self.triangles.into_owned(),
+            shared_normals: self.shared_normals,
+        }
+    }
 }
 
-impl Save for NiTriShapeData {
-    fn save(&self, stream: &mut Writer) -> io::Result<()> {
-        stream.save(&self.base)?;
-        stream.save_as::<u16>(self.triangles.len())?;
-        stream.save_as::<u32>(self.triangles.len() * 3)?;
-        stream.save_vec(&self.triangles)?;
-        stream.save_as::<u16>(self.shared_normals.len())?;
-        for indices in &self.shared_normals {
-            stream.save_as::<u16>(indices.len())?;
-            stream.save_vec(indices)?;
+impl<'a> From<&'a NiTriShapeData> for NiTriShapeDataRef<'a> {
+    fn from(data: &'a NiTriShapeData) -> Self {
+        Self {
+            base: NiGeometryDataRef::from(&data.base.base),
+            triangles: Cow::Borrowed(&data.triangles),
+            shared_normals: data.shared_normals.clone(),
+        }
+    }
+}
+
+impl From<NiTriShapeData> for NiTriShapeDataRef<'static> {
+    fn from(data: NiTriShapeData) -> Self {
+        Self {
+            base: NiGeometryDataRef::from(data.base.base),
+            triangles: Cow::Owned(data.triangles),
+            shared_normals: data.shared_normals,
         }
-        Ok(())
     }
 }
@@ -1,21 +1,34 @@
 // internal imports
 use crate::prelude::*;
 
-#[derive(Meta, Clone, Debug, Default, PartialEq)]
+#[derive(Meta, Clone, Debug, PartialEq, SmartDefault)]
 pub struct NiZBufferProperty {
     pub base: NiProperty,
+    #[default(ZBufferTestFunction::LessEqual)]
+    pub function: ZBufferTestFunction, // since 4.1.0.12
 }
 
 impl Load for NiZBufferProperty {
     fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
         let base = stream.load()?;
-        Ok(Self { base })
+        let function = if stream.version >= version::V4_1_0_12 {
+            let function = stream.load_as::<u32, u16>()?;
+            ZBufferTestFunction::try_from(function).or_else(|()| {
+                Reader::invalid_enum_value(format!("Invalid ZBufferTestFunction: {function}"))
+            })?
+        } else {
+            ZBufferTestFunction::LessEqual
+        };
+        Ok(Self { base, function })
     }
 }
 
 impl Save for NiZBufferProperty {
     fn save(&self, stream: &mut Writer) -> io::Result<()> {
         stream.save(&self.base)?;
+        if stream.version >= version::V4_1_0_12 {
+            stream.save_as::<u32>(self.function as u16)?;
+        }
         Ok(())
     }
 }
@@ -99,7 +99,7 @@ pub fn derive_nitype(input: TokenStream) -> TokenStream {
     let idents: Vec<_> = generated_variants.iter().map(|v| &v.ident).collect();
 
     // the idents as byte literals
-    let idents_bytes = idents.iter().map(|id| get_literal_byte_str(id));
+    let idents_bytes: Vec<_> = idents.iter().map(|id| get_literal_byte_str(id)).collect();
 
     let impl_try_from = impl_try_from_nitype(&idents);
 
@@ -108,18 +108,29 @@ pub fn derive_nitype(input: TokenStream) -> TokenStream {
             use crate::prelude::*;
             use io::{Read, Write};
 
-            impl Load for NiType {
-                fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
-                    let type_name: ::bstr::BString = stream.load()?;
-                    match type_name.as_slice() {
+            impl NiType {
+                /// Returns true if `type_name` names a block type this crate loads
+                pub fn is_known_type_name(type_name: &[u8]) -> bool {
+                    matches!(type_name, #(#idents_bytes)|*)
+                }
+
+                /// Load the block that follows `type_name`, once the type name has been read
+                pub fn load_named(type_name: &[u8], stream: &mut Reader<'_>) -> io::Result<Self> {
+                    match type_name {
                         #(
                             #idents_bytes => Ok(Self::#idents(stream.load()?)),
                         )*
                         // TODO: more detailed error information!
-                        _ => Reader::error(format!("Invalid Type: {}", type_name))?,
+                        _ => Reader::error(format!("Invalid Type: {}", ::bstr::BStr::new(type_name)))?,
                     }
                 }
             }
+            impl Load for NiType {
+                fn load(stream: &mut Reader<'_>) -> io::Result<Self> {
+                    let type_name: ::bstr::BString = stream.load()?;
+                    Self::load_named(&type_name, stream)
+                }
+            }
             impl Save for NiType {
                 fn save(&self, stream: &mut Writer) -> io::Result<()> {
                     match self {